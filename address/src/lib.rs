@@ -69,6 +69,37 @@ pub mod secret {
         }
     }
 }
+pub mod multisig {
+    use super::*;
+    pub fn encode(threshold: u8, addresses: &[AddressBytes]) -> String {
+        let bytes = [&[threshold][..], &addresses.concat()].concat();
+        [PREFIX_MULTISIG, &hex::encode(&bytes), &hex::encode(checksum(&bytes))].concat()
+    }
+    pub fn decode(str: &str) -> Result<(u8, Vec<AddressBytes>), Box<dyn Error>> {
+        let decoded = hex::decode(str.replacen(PREFIX_MULTISIG, "", 1))?;
+        if decoded.len() < 5 {
+            return Err("invalid multisig".into());
+        }
+        let (bytes, checksum_bytes) = decoded.split_at(decoded.len() - 4);
+        if checksum(bytes) != checksum_bytes {
+            return Err("checksum mismatch".into());
+        }
+        let (threshold, addresses) = bytes.split_first().unwrap();
+        if addresses.len() % 20 != 0 {
+            return Err("invalid multisig addresses".into());
+        }
+        Ok((*threshold, addresses.chunks(20).map(|x| x.try_into().unwrap()).collect()))
+    }
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        #[test]
+        fn test_encode_decode() {
+            let addresses = vec![[0; 20], [1; 20]];
+            assert_eq!(decode(&encode(2, &addresses)).unwrap(), (2, addresses));
+        }
+    }
+}
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -11,6 +11,12 @@ pub struct Info {
     pub heartbeats: usize,
     pub tree_size: usize,
     pub lag: f64,
+    pub pruned: Option<Pruned>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pruned {
+    pub from: usize,
+    pub to: usize,
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Sync {
@@ -18,10 +24,41 @@ pub struct Sync {
     pub height: usize,
     pub last_seen: String,
 }
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Finalized {
+    pub hash: String,
+    pub votes: usize,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Supply {
+    pub supply: Amount,
+    pub burned: Amount,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pending {
+    pub hash: String,
+    pub sender: String,
+    pub fee: Amount,
+    pub size: usize,
+    pub age: u32,
+}
 pub type Height = usize;
 pub type Amount = String;
+pub type Nonce = u64;
 pub type Hash = String;
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Unbonding {
+    pub amount: Amount,
+    pub blocks: usize,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct History {
+    pub hash: Hash,
+    pub block: Hash,
+    pub height: usize,
+    pub direction: String,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Dynamic {
     pub random_queue: Vec<String>,
     pub hashes: usize,
@@ -36,6 +73,8 @@ pub struct Trusted {
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Options {
+    pub chain: String,
+    pub chain_id: u32,
     pub mint: bool,
     pub tempdb: bool,
     pub tempkey: bool,
@@ -43,6 +82,7 @@ pub struct Options {
     pub pending: usize,
     pub ban_offline: usize,
     pub time_delta: u32,
+    pub prune: Option<usize>,
     pub max_established: Option<u32>,
     pub tps: f64,
     pub bind_api: String,
@@ -60,36 +100,74 @@ pub struct Block {
     pub beta: String,
     pub transactions: Vec<String>,
     pub stakes: Vec<String>,
+    pub evidence: Vec<String>,
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Transaction {
     pub hash: String,
+    pub nonce: u64,
     pub input_address: String,
-    pub output_address: String,
+    pub outputs: Vec<(String, Amount)>,
     pub amount: Amount,
     pub fee: Amount,
+    pub memo: String,
+    pub multisig: Option<String>,
     pub timestamp: u32,
     pub signature: String,
+    pub block: Option<String>,
+    pub height: Option<usize>,
+    pub confirmations: usize,
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TransactionStatus {
+    pub status: String,
+    pub block: Option<String>,
+    pub height: Option<usize>,
+    pub replaced_by: Option<String>,
+    pub reason: Option<String>,
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Stake {
     pub hash: String,
+    pub version: u8,
     pub address: String,
+    pub amount: Amount,
     pub fee: Amount,
     pub deposit: bool,
+    pub delegate: Option<String>,
+    pub commission: u8,
     pub timestamp: u32,
     pub signature: String,
+    pub block: Option<String>,
+    pub height: Option<usize>,
+    pub confirmations: usize,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Delegation {
+    pub staker: String,
+    pub delegator: String,
+    pub amount: Amount,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Lock {
+    pub hash: String,
+    pub sender: String,
+    pub recipient: String,
+    pub amount: Amount,
+    pub hash_lock: String,
+    pub timeout: u32,
+    pub status: String,
 }
 enum Method {
-    GET,
-    POST,
+    Get,
+    Post,
 }
 fn request_line(method: Method, path: &str) -> String {
     format!(
         "{} {} HTTP/1.1",
         match method {
-            Method::GET => "GET",
-            Method::POST => "POST",
+            Method::Get => "GET",
+            Method::Post => "POST",
         },
         path
     )
@@ -98,15 +176,18 @@ async fn request(addr: &str, method: Method, path: &str, body: Option<&str>) ->
     let mut stream = TcpStream::connect(addr).await?;
     stream.write_all(request_line(method, path).as_bytes()).await?;
     if let Some(body) = body {
-        stream.write_all(b"\n\n").await?;
+        stream.write_all(format!("\nContent-Length: {}\n\n", body.len()).as_bytes()).await?;
         stream.write_all(body.as_bytes()).await?;
     }
     let mut buffer = String::new();
     stream.read_to_string(&mut buffer).await?;
-    Ok(parse_body(buffer)?)
+    parse_body(buffer)
 }
 fn parse_body(buffer: String) -> Result<String, Box<dyn Error>> {
     let vec = buffer.split("\n\n").collect::<Vec<&str>>();
+    if buffer.starts_with("HTTP/1.1 410") {
+        return Err(vec.get(1).unwrap_or(&"gone").to_string().into());
+    }
     let body = vec.get(1).ok_or("empty body")?;
     Ok(body.to_string())
 }
@@ -114,48 +195,105 @@ pub mod get {
     use super::*;
     use std::error::Error;
     pub async fn index(api: &str) -> Result<Index, Box<dyn Error>> {
-        Ok(request(api, Method::GET, "/", None).await?)
+        request(api, Method::Get, "/", None).await
     }
     pub async fn info(api: &str) -> Result<Info, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, "/info", None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, "/info", None).await?)?)
     }
     pub async fn sync(api: &str) -> Result<Sync, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, "/sync", None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, "/sync", None).await?)?)
+    }
+    pub async fn pending_transactions(api: &str) -> Result<Vec<Pending>, Box<dyn Error>> {
+        Ok(serde_json::from_str(&request(api, Method::Get, "/pending/transactions", None).await?)?)
+    }
+    pub async fn pending_stakes(api: &str) -> Result<Vec<Pending>, Box<dyn Error>> {
+        Ok(serde_json::from_str(&request(api, Method::Get, "/pending/stakes", None).await?)?)
+    }
+    pub async fn pending_blocks(api: &str) -> Result<Vec<Pending>, Box<dyn Error>> {
+        Ok(serde_json::from_str(&request(api, Method::Get, "/pending/blocks", None).await?)?)
+    }
+    pub async fn pending_transaction(api: &str, hash: &str) -> Result<Transaction, Box<dyn Error>> {
+        Ok(serde_json::from_str(
+            &request(api, Method::Get, &format!("/pending/transaction/{}", hash), None).await?,
+        )?)
+    }
+    pub async fn supply(api: &str) -> Result<Supply, Box<dyn Error>> {
+        Ok(serde_json::from_str(&request(api, Method::Get, "/supply", None).await?)?)
+    }
+    pub async fn finalized(api: &str) -> Result<Option<Finalized>, Box<dyn Error>> {
+        Ok(serde_json::from_str(&request(api, Method::Get, "/finalized", None).await?)?)
     }
     pub async fn height(api: &str) -> Result<Height, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, "/height", None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, "/height", None).await?)?)
     }
     pub async fn balance(api: &str, address: &str) -> Result<Amount, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, &format!("/balance/{}", address), None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, &format!("/balance/{}", address), None).await?)?)
     }
     pub async fn staked(api: &str, address: &str) -> Result<Amount, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, &format!("/staked/{}", address), None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, &format!("/staked/{}", address), None).await?)?)
+    }
+    pub async fn unbonding(api: &str, address: &str) -> Result<Vec<Unbonding>, Box<dyn Error>> {
+        Ok(serde_json::from_str(
+            &request(api, Method::Get, &format!("/unbonding/{}", address), None).await?,
+        )?)
+    }
+    pub async fn delegators(api: &str, address: &str) -> Result<Vec<Delegation>, Box<dyn Error>> {
+        Ok(serde_json::from_str(
+            &request(api, Method::Get, &format!("/delegators/{}", address), None).await?,
+        )?)
+    }
+    pub async fn delegations(api: &str, address: &str) -> Result<Vec<Delegation>, Box<dyn Error>> {
+        Ok(serde_json::from_str(
+            &request(api, Method::Get, &format!("/delegations/{}", address), None).await?,
+        )?)
+    }
+    pub async fn history(api: &str, address: &str, offset: usize, limit: usize) -> Result<Vec<History>, Box<dyn Error>> {
+        Ok(serde_json::from_str(
+            &request(
+                api,
+                Method::Get,
+                &format!("/address/{}/history?offset={}&limit={}", address, offset, limit),
+                None,
+            )
+            .await?,
+        )?)
+    }
+    pub async fn nonce(api: &str, address: &str) -> Result<Nonce, Box<dyn Error>> {
+        Ok(serde_json::from_str(&request(api, Method::Get, &format!("/nonce/{}", address), None).await?)?)
     }
     pub async fn hash(api: &str, height: &usize) -> Result<Hash, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, &format!("/hash/{}", height), None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, &format!("/hash/{}", height), None).await?)?)
     }
     pub async fn dynamic(api: &str) -> Result<Dynamic, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, "/dynamic", None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, "/dynamic", None).await?)?)
     }
     pub async fn trusted(api: &str) -> Result<Trusted, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, "/trusted", None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, "/trusted", None).await?)?)
     }
     pub async fn options(api: &str) -> Result<Options, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, "/options", None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, "/options", None).await?)?)
     }
     pub async fn block(api: &str, hash: &str) -> Result<Block, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, &format!("/block/{}", hash), None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, &format!("/block/{}", hash), None).await?)?)
     }
     pub async fn latest_block(api: &str) -> Result<Block, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, "/block/latest", None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, "/block/latest", None).await?)?)
     }
     pub async fn transaction(api: &str, hash: &str) -> Result<Transaction, Box<dyn Error>> {
         Ok(serde_json::from_str(
-            &request(api, Method::GET, &format!("/transaction/{}", hash), None).await?,
+            &request(api, Method::Get, &format!("/transaction/{}", hash), None).await?,
+        )?)
+    }
+    pub async fn transaction_status(api: &str, hash: &str) -> Result<TransactionStatus, Box<dyn Error>> {
+        Ok(serde_json::from_str(
+            &request(api, Method::Get, &format!("/transaction/{}/status", hash), None).await?,
         )?)
     }
     pub async fn stake(api: &str, hash: &str) -> Result<Stake, Box<dyn Error>> {
-        Ok(serde_json::from_str(&request(api, Method::GET, &format!("/stake/{}", hash), None).await?)?)
+        Ok(serde_json::from_str(&request(api, Method::Get, &format!("/stake/{}", hash), None).await?)?)
+    }
+    pub async fn lock(api: &str, hash: &str) -> Result<Lock, Box<dyn Error>> {
+        Ok(serde_json::from_str(&request(api, Method::Get, &format!("/lock/{}", hash), None).await?)?)
     }
 }
 pub mod post {
@@ -165,12 +303,12 @@ pub mod post {
     use std::error::Error;
     pub async fn transaction(api: &str, transaction_b: &TransactionB) -> Result<String, Box<dyn Error>> {
         Ok(serde_json::from_str(
-            &request(api, Method::POST, "/transaction", Some(&serde_json::to_string(transaction_b)?)).await?,
+            &request(api, Method::Post, "/transaction", Some(&serde_json::to_string(transaction_b)?)).await?,
         )?)
     }
     pub async fn stake(api: &str, stake_b: &StakeB) -> Result<String, Box<dyn Error>> {
         Ok(serde_json::from_str(
-            &request(api, Method::POST, "/stake", Some(&serde_json::to_string(stake_b)?)).await?,
+            &request(api, Method::Post, "/stake", Some(&serde_json::to_string(stake_b)?)).await?,
         )?)
     }
 }
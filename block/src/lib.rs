@@ -8,16 +8,21 @@ use serde_big_array::BigArray;
 use sha2::{Digest, Sha256};
 use std::error::Error;
 pub trait Block {
+    fn get_chain_id(&self) -> u32;
     fn get_previous_hash(&self) -> &Hash;
     fn get_merkle_root_transaction(&self) -> MerkleRoot;
     fn get_merkle_root_stake(&self) -> MerkleRoot;
+    fn get_merkle_root_evidence(&self) -> MerkleRoot;
     fn get_timestamp(&self) -> u32;
     fn get_pi(&self) -> &Pi;
     fn hash(&self) -> Hash;
-    fn hash_input(&self) -> [u8; 181];
+    fn hash_input(&self) -> [u8; 217];
     fn beta(&self) -> Result<Beta, Box<dyn Error>>;
 }
 impl Block for BlockA {
+    fn get_chain_id(&self) -> u32 {
+        self.chain_id
+    }
     fn get_previous_hash(&self) -> &Hash {
         &self.previous_hash
     }
@@ -27,6 +32,9 @@ impl Block for BlockA {
     fn get_merkle_root_stake(&self) -> MerkleRoot {
         merkle_root(&self.stake_hashes())
     }
+    fn get_merkle_root_evidence(&self) -> MerkleRoot {
+        merkle_root(&self.evidence_hashes())
+    }
     fn get_timestamp(&self) -> u32 {
         self.timestamp
     }
@@ -36,7 +44,7 @@ impl Block for BlockA {
     fn hash(&self) -> Hash {
         hash(self)
     }
-    fn hash_input(&self) -> [u8; 181] {
+    fn hash_input(&self) -> [u8; 217] {
         hash_input(self)
     }
     fn beta(&self) -> Result<Beta, Box<dyn Error>> {
@@ -44,6 +52,9 @@ impl Block for BlockA {
     }
 }
 impl Block for BlockB {
+    fn get_chain_id(&self) -> u32 {
+        self.chain_id
+    }
     fn get_previous_hash(&self) -> &Hash {
         &self.previous_hash
     }
@@ -53,6 +64,9 @@ impl Block for BlockB {
     fn get_merkle_root_stake(&self) -> MerkleRoot {
         merkle_root(&self.stake_hashes())
     }
+    fn get_merkle_root_evidence(&self) -> MerkleRoot {
+        merkle_root(&self.evidence_hashes())
+    }
     fn get_timestamp(&self) -> u32 {
         self.timestamp
     }
@@ -62,7 +76,39 @@ impl Block for BlockB {
     fn hash(&self) -> Hash {
         hash(self)
     }
-    fn hash_input(&self) -> [u8; 181] {
+    fn hash_input(&self) -> [u8; 217] {
+        hash_input(self)
+    }
+    fn beta(&self) -> Result<Beta, Box<dyn Error>> {
+        beta(self)
+    }
+}
+impl Block for BlockC {
+    fn get_chain_id(&self) -> u32 {
+        self.chain_id
+    }
+    fn get_previous_hash(&self) -> &Hash {
+        &self.previous_hash
+    }
+    fn get_merkle_root_transaction(&self) -> MerkleRoot {
+        merkle_root(&self.transaction_hashes)
+    }
+    fn get_merkle_root_stake(&self) -> MerkleRoot {
+        merkle_root(&self.stake_hashes)
+    }
+    fn get_merkle_root_evidence(&self) -> MerkleRoot {
+        merkle_root(&self.evidence_hashes)
+    }
+    fn get_timestamp(&self) -> u32 {
+        self.timestamp
+    }
+    fn get_pi(&self) -> &Pi {
+        &self.pi
+    }
+    fn hash(&self) -> Hash {
+        hash(self)
+    }
+    fn hash_input(&self) -> [u8; 217] {
         hash_input(self)
     }
     fn beta(&self) -> Result<Beta, Box<dyn Error>> {
@@ -71,6 +117,7 @@ impl Block for BlockB {
 }
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct BlockA {
+    pub chain_id: u32,
     pub hash: Hash,
     pub previous_hash: Hash,
     pub timestamp: u32,
@@ -83,9 +130,11 @@ pub struct BlockA {
     pub signature: SignatureBytes,
     pub transactions: Vec<TransactionA>,
     pub stakes: Vec<StakeA>,
+    pub evidence: Vec<Evidence>,
 }
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct BlockB {
+    pub chain_id: u32,
     pub previous_hash: Hash,
     pub timestamp: u32,
     #[serde(with = "BigArray")]
@@ -94,9 +143,11 @@ pub struct BlockB {
     pub pi: Pi,
     pub transactions: Vec<TransactionB>,
     pub stakes: Vec<StakeB>,
+    pub evidence: Vec<Evidence>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BlockC {
+    pub chain_id: u32,
     pub previous_hash: Hash,
     pub timestamp: u32,
     #[serde(with = "BigArray")]
@@ -105,31 +156,50 @@ pub struct BlockC {
     pub pi: Pi,
     pub transaction_hashes: Vec<Hash>,
     pub stake_hashes: Vec<Hash>,
+    pub evidence_hashes: Vec<Hash>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Evidence {
+    pub block_c_0: BlockC,
+    pub block_c_1: BlockC,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Checkpoint {
+    pub chain_id: u32,
+    pub block_hash: Hash,
+    #[serde(with = "BigArray")]
+    pub signature: SignatureBytes,
 }
 impl BlockA {
     pub fn b(&self) -> BlockB {
         BlockB {
+            chain_id: self.chain_id,
             previous_hash: self.previous_hash,
             timestamp: self.timestamp,
             signature: self.signature,
             pi: self.pi,
             transactions: self.transactions.iter().map(|x| x.b()).collect(),
             stakes: self.stakes.iter().map(|x| x.b()).collect(),
+            evidence: self.evidence.clone(),
         }
     }
     pub fn hash(&self) -> Hash {
         hash(self)
     }
+    #[allow(clippy::too_many_arguments)]
     pub fn sign(
+        chain_id: u32,
         previous_hash: Hash,
         timestamp: u32,
         transactions: Vec<TransactionA>,
         stakes: Vec<StakeA>,
+        evidence: Vec<Evidence>,
         key: &Key,
         previous_beta: &[u8],
     ) -> Result<BlockA, Box<dyn Error>> {
         let pi = key.vrf_prove(previous_beta).ok_or("failed to generate proof")?;
         let mut block_a = BlockA {
+            chain_id,
             hash: [0; 32],
             previous_hash,
             timestamp,
@@ -139,6 +209,7 @@ impl BlockA {
             signature: [0; 64],
             transactions,
             stakes,
+            evidence,
         };
         block_a.beta = block_a.beta()?;
         block_a.hash = block_a.hash();
@@ -149,10 +220,10 @@ impl BlockA {
     pub fn input_address(&self) -> AddressBytes {
         Key::address(&self.input_public_key)
     }
-    pub fn reward(&self) -> u128 {
-        self.fees() + COIN
+    pub fn reward(&self, block_reward: u128) -> u128 {
+        self.fees() + block_reward
     }
-    fn fees(&self) -> u128 {
+    pub fn fees(&self) -> u128 {
         let mut fees = 0;
         for transaction in self.transactions.iter() {
             fees += transaction.fee;
@@ -168,6 +239,9 @@ impl BlockA {
     fn stake_hashes(&self) -> Vec<Hash> {
         self.stakes.iter().map(|x| x.hash()).collect()
     }
+    fn evidence_hashes(&self) -> Vec<Hash> {
+        self.evidence.iter().map(|x| x.hash()).collect()
+    }
 }
 impl BlockB {
     pub fn a(&self) -> Result<BlockA, Box<dyn Error>> {
@@ -180,6 +254,7 @@ impl BlockB {
             stakes.push(stake.a(None)?);
         }
         Ok(BlockA {
+            chain_id: self.chain_id,
             hash: self.hash(),
             previous_hash: self.previous_hash,
             timestamp: self.timestamp,
@@ -189,16 +264,19 @@ impl BlockB {
             signature: self.signature,
             transactions,
             stakes,
+            evidence: self.evidence.clone(),
         })
     }
     pub fn c(&self) -> BlockC {
         BlockC {
+            chain_id: self.chain_id,
             previous_hash: self.previous_hash,
             timestamp: self.timestamp,
             signature: self.signature,
             pi: self.pi,
             transaction_hashes: self.transaction_hashes(),
             stake_hashes: self.stake_hashes(),
+            evidence_hashes: self.evidence_hashes(),
         }
     }
     pub fn hash(&self) -> Hash {
@@ -210,8 +288,11 @@ impl BlockB {
     fn stake_hashes(&self) -> Vec<Hash> {
         self.stakes.iter().map(|x| x.hash()).collect()
     }
+    fn evidence_hashes(&self) -> Vec<Hash> {
+        self.evidence.iter().map(|x| x.hash()).collect()
+    }
     fn input_public_key(&self) -> Result<PublicKeyBytes, Box<dyn Error>> {
-        Ok(Key::recover(&self.hash(), &self.signature)?)
+        Key::recover(&self.hash(), &self.signature)
     }
 }
 impl BlockC {
@@ -219,10 +300,15 @@ impl BlockC {
         &self,
         transactions: Vec<TransactionA>,
         stakes: Vec<StakeA>,
+        evidence: Vec<Evidence>,
         beta: Option<[u8; 32]>,
         input_public_key: Option<PublicKeyBytes>,
     ) -> Result<BlockA, Box<dyn Error>> {
-        let block_b = self.b(transactions.iter().map(|x| x.b()).collect(), stakes.iter().map(|x| x.b()).collect());
+        let block_b = self.b(
+            transactions.iter().map(|x| x.b()).collect(),
+            stakes.iter().map(|x| x.b()).collect(),
+            evidence.clone(),
+        );
         let beta = match beta {
             Some(x) => x,
             None => block_b.beta()?,
@@ -232,6 +318,7 @@ impl BlockC {
             None => block_b.input_public_key()?,
         };
         let mut block_a = BlockA {
+            chain_id: self.chain_id,
             hash: [0; 32],
             previous_hash: self.previous_hash,
             timestamp: self.timestamp,
@@ -241,24 +328,94 @@ impl BlockC {
             signature: self.signature,
             transactions,
             stakes,
+            evidence,
         };
         block_a.hash = block_a.hash();
         Ok(block_a)
     }
-    pub fn b(&self, transactions: Vec<TransactionB>, stakes: Vec<StakeB>) -> BlockB {
+    pub fn b(&self, transactions: Vec<TransactionB>, stakes: Vec<StakeB>, evidence: Vec<Evidence>) -> BlockB {
         BlockB {
+            chain_id: self.chain_id,
             previous_hash: self.previous_hash,
             timestamp: self.timestamp,
             signature: self.signature,
             pi: self.pi,
             transactions,
             stakes,
+            evidence,
+        }
+    }
+    pub fn hash(&self) -> Hash {
+        hash(self)
+    }
+    pub fn input_public_key(&self) -> Result<PublicKeyBytes, Box<dyn Error>> {
+        Key::recover(&self.hash(), &self.signature)
+    }
+}
+impl Evidence {
+    pub fn new(block_c_0: BlockC, block_c_1: BlockC) -> Evidence {
+        if block_c_0.hash() <= block_c_1.hash() {
+            Evidence { block_c_0, block_c_1 }
+        } else {
+            Evidence {
+                block_c_0: block_c_1,
+                block_c_1: block_c_0,
+            }
+        }
+    }
+    pub fn hash(&self) -> Hash {
+        let mut hasher = Sha256::new();
+        hasher.update(self.block_c_0.hash());
+        hasher.update(self.block_c_1.hash());
+        hasher.finalize().into()
+    }
+    pub fn input_public_key(&self) -> Result<PublicKeyBytes, Box<dyn Error>> {
+        if self.block_c_0.hash() >= self.block_c_1.hash() {
+            return Err("evidence blocks order".into());
+        }
+        if self.block_c_0.chain_id != self.block_c_1.chain_id {
+            return Err("evidence chain id".into());
         }
+        if self.block_c_0.previous_hash != self.block_c_1.previous_hash {
+            return Err("evidence previous hash".into());
+        }
+        let input_public_key = self.block_c_0.input_public_key()?;
+        if input_public_key != self.block_c_1.input_public_key()? {
+            return Err("evidence input public key".into());
+        }
+        Ok(input_public_key)
+    }
+    pub fn input_address(&self) -> Result<AddressBytes, Box<dyn Error>> {
+        Ok(Key::address(&self.input_public_key()?))
+    }
+}
+impl Checkpoint {
+    pub fn sign(chain_id: u32, block_hash: Hash, key: &Key) -> Result<Checkpoint, Box<dyn Error>> {
+        let mut checkpoint = Checkpoint {
+            chain_id,
+            block_hash,
+            signature: [0; 64],
+        };
+        checkpoint.signature = key.sign(&checkpoint.hash())?;
+        Ok(checkpoint)
+    }
+    pub fn hash(&self) -> Hash {
+        let mut hasher = Sha256::new();
+        hasher.update(self.chain_id.to_be_bytes());
+        hasher.update(self.block_hash);
+        hasher.finalize().into()
+    }
+    pub fn input_public_key(&self) -> Result<PublicKeyBytes, Box<dyn Error>> {
+        Key::recover(&self.hash(), &self.signature)
+    }
+    pub fn input_address(&self) -> Result<AddressBytes, Box<dyn Error>> {
+        Ok(Key::address(&self.input_public_key()?))
     }
 }
 impl Default for BlockA {
     fn default() -> Self {
         BlockA {
+            chain_id: 0,
             hash: [0; 32],
             previous_hash: [0; 32],
             timestamp: 0,
@@ -268,45 +425,52 @@ impl Default for BlockA {
             signature: [0; 64],
             transactions: vec![],
             stakes: vec![],
+            evidence: vec![],
         }
     }
 }
 impl Default for BlockB {
     fn default() -> Self {
         BlockB {
+            chain_id: 0,
             previous_hash: [0; 32],
             timestamp: 0,
             signature: [0; 64],
             pi: [0; 81],
             transactions: vec![],
             stakes: vec![],
+            evidence: vec![],
         }
     }
 }
 impl Default for BlockC {
     fn default() -> Self {
         BlockC {
+            chain_id: 0,
             previous_hash: [0; 32],
             timestamp: 0,
             signature: [0; 64],
             pi: [0; 81],
             transaction_hashes: vec![],
             stake_hashes: vec![],
+            evidence_hashes: vec![],
         }
     }
 }
 fn hash<T: Block>(block: &T) -> Hash {
     let mut hasher = Sha256::new();
-    hasher.update(&block.hash_input());
+    hasher.update(block.hash_input());
     hasher.finalize().into()
 }
-fn hash_input<T: Block>(block: &T) -> [u8; 181] {
-    let mut bytes = [0; 181];
-    bytes[0..32].copy_from_slice(block.get_previous_hash());
-    bytes[32..64].copy_from_slice(&block.get_merkle_root_transaction());
-    bytes[64..96].copy_from_slice(&block.get_merkle_root_stake());
-    bytes[96..100].copy_from_slice(&block.get_timestamp().to_be_bytes());
-    bytes[100..181].copy_from_slice(block.get_pi());
+fn hash_input<T: Block>(block: &T) -> [u8; 217] {
+    let mut bytes = [0; 217];
+    bytes[0..4].copy_from_slice(&block.get_chain_id().to_be_bytes());
+    bytes[4..36].copy_from_slice(block.get_previous_hash());
+    bytes[36..68].copy_from_slice(&block.get_merkle_root_transaction());
+    bytes[68..100].copy_from_slice(&block.get_merkle_root_stake());
+    bytes[100..132].copy_from_slice(&block.get_merkle_root_evidence());
+    bytes[132..136].copy_from_slice(&block.get_timestamp().to_be_bytes());
+    bytes[136..217].copy_from_slice(block.get_pi());
     bytes
 }
 fn merkle_root(hashes: &[Hash]) -> MerkleRoot {
@@ -320,8 +484,8 @@ fn merkle_root(hashes: &[Hash]) -> MerkleRoot {
             hasher.finalize().into()
         }
     }
-    type CBMT = ExCBMT<[u8; 32], Hasher>;
-    CBMT::build_merkle_root(hashes)
+    type Cbmt = ExCBMT<[u8; 32], Hasher>;
+    Cbmt::build_merkle_root(hashes)
 }
 fn beta<T: Block>(block: &T) -> Result<Beta, Box<dyn Error>> {
     Key::vrf_proof_to_hash(block.get_pi()).ok_or("invalid beta".into())
@@ -333,7 +497,31 @@ mod tests {
     fn test_hash() {
         assert_eq!(
             BlockB::default().hash(),
-            [219, 36, 84, 162, 32, 189, 146, 241, 148, 53, 36, 177, 50, 142, 92, 103, 125, 225, 26, 208, 20, 86, 5, 216, 113, 32, 54, 141, 75, 147, 221, 219]
+            [
+                8, 127, 229, 93, 70, 181, 250, 197, 226, 51, 188, 114, 233, 240, 57, 35, 101, 218, 197, 255, 9, 206, 228, 39, 140, 172, 168, 102, 145, 119, 22,
+                37
+            ]
         );
+        assert_eq!(BlockC::default().hash(), BlockB::default().hash());
+    }
+    #[test]
+    fn test_evidence() {
+        let key = Key::generate();
+        let block_a_0 = BlockA::sign(0, [0; 32], 0, vec![], vec![], vec![], &key, &GENESIS_BETA).unwrap();
+        let block_a_1 = BlockA::sign(0, [0; 32], 1, vec![], vec![], vec![], &key, &GENESIS_BETA).unwrap();
+        let evidence = Evidence::new(block_a_0.b().c(), block_a_1.b().c());
+        assert_eq!(evidence.input_address().unwrap(), key.address_bytes());
+        assert_eq!(evidence.hash(), Evidence::new(block_a_1.b().c(), block_a_0.b().c()).hash());
+        assert!(Evidence::new(block_a_0.b().c(), block_a_0.b().c()).input_address().is_err());
+        let block_a_2 = BlockA::sign(0, [0; 32], 1, vec![], vec![], vec![], &Key::generate(), &GENESIS_BETA).unwrap();
+        assert!(Evidence::new(block_a_0.b().c(), block_a_2.b().c()).input_address().is_err());
+    }
+    #[test]
+    fn test_checkpoint() {
+        let key = Key::generate();
+        let checkpoint = Checkpoint::sign(0, [0; 32], &key).unwrap();
+        assert_eq!(checkpoint.input_address().unwrap(), key.address_bytes());
+        let checkpoint = Checkpoint { chain_id: 1, ..checkpoint };
+        assert_ne!(checkpoint.input_address().ok(), Some(key.address_bytes()));
     }
 }
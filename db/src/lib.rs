@@ -1,4 +1,5 @@
-use rocksdb::{ColumnFamily, ColumnFamilyDescriptor, DBWithThreadMode, Options, SingleThreaded, DB};
+use rocksdb::{ColumnFamily, ColumnFamilyDescriptor, DBWithThreadMode, IteratorMode, Options, SingleThreaded, WriteBatch, DB};
+use std::error::Error;
 fn descriptors() -> Vec<ColumnFamilyDescriptor> {
     let options = Options::default();
     vec![
@@ -9,7 +10,17 @@ fn descriptors() -> Vec<ColumnFamilyDescriptor> {
         ColumnFamilyDescriptor::new("peers", options.clone()),
         ColumnFamilyDescriptor::new("input addresses", options.clone()),
         ColumnFamilyDescriptor::new("input public keys", options.clone()),
-        ColumnFamilyDescriptor::new("betas", options),
+        ColumnFamilyDescriptor::new("betas", options.clone()),
+        ColumnFamilyDescriptor::new("evidence", options.clone()),
+        ColumnFamilyDescriptor::new("pending transactions", options.clone()),
+        ColumnFamilyDescriptor::new("pending stakes", options.clone()),
+        ColumnFamilyDescriptor::new("inclusions", options.clone()),
+        ColumnFamilyDescriptor::new("history", options.clone()),
+        ColumnFamilyDescriptor::new("heights", options.clone()),
+        ColumnFamilyDescriptor::new("block heights", options.clone()),
+        ColumnFamilyDescriptor::new("snapshots", options.clone()),
+        ColumnFamilyDescriptor::new("pruned", options.clone()),
+        ColumnFamilyDescriptor::new("finalized", options),
     ]
 }
 pub fn open(path: &str) -> DBWithThreadMode<SingleThreaded> {
@@ -18,6 +29,13 @@ pub fn open(path: &str) -> DBWithThreadMode<SingleThreaded> {
     options.create_if_missing(true);
     DB::open_cf_descriptors(&options, path, descriptors()).unwrap()
 }
+fn clear(batch: &mut WriteBatch, db: &DBWithThreadMode<SingleThreaded>, cf: &ColumnFamily) -> Result<(), Box<dyn Error>> {
+    for res in db.iterator_cf(cf, IteratorMode::Start) {
+        let (key, _) = res?;
+        batch.delete_cf(cf, key);
+    }
+    Ok(())
+}
 pub fn blocks(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
     db.cf_handle("blocks").unwrap()
 }
@@ -42,19 +60,57 @@ pub fn input_public_keys(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily
 pub fn betas(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
     db.cf_handle("betas").unwrap()
 }
+pub fn evidence(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
+    db.cf_handle("evidence").unwrap()
+}
+pub fn pending_transactions(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
+    db.cf_handle("pending transactions").unwrap()
+}
+pub fn pending_stakes(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
+    db.cf_handle("pending stakes").unwrap()
+}
+pub fn inclusions(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
+    db.cf_handle("inclusions").unwrap()
+}
+pub fn history(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
+    db.cf_handle("history").unwrap()
+}
+pub fn heights(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
+    db.cf_handle("heights").unwrap()
+}
+pub fn block_heights(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
+    db.cf_handle("block heights").unwrap()
+}
+pub fn snapshots(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
+    db.cf_handle("snapshots").unwrap()
+}
+pub fn pruned(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
+    db.cf_handle("pruned").unwrap()
+}
+pub fn finalized(db: &DBWithThreadMode<SingleThreaded>) -> &ColumnFamily {
+    db.cf_handle("finalized").unwrap()
+}
 pub mod block {
-    use super::{beta, input_public_key, stake, transaction};
+    use super::{beta, evidence, height, inclusion, input_public_key, stake, transaction};
     use pea_block::{BlockA, BlockB, BlockC};
-    use rocksdb::{DBWithThreadMode, SingleThreaded};
+    use rocksdb::{DBWithThreadMode, SingleThreaded, WriteBatch};
     use std::error::Error;
     pub fn put(block_a: &BlockA, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
+        let mut batch = WriteBatch::default();
+        height::put(&mut batch, &block_a.hash, height::next(db, &block_a.previous_hash)?, db);
         for transaction_a in block_a.transactions.iter() {
-            transaction::put(transaction_a, db)?;
+            transaction::put(&mut batch, transaction_a, db)?;
+            inclusion::put(&mut batch, &transaction_a.hash, &block_a.hash, db)?;
         }
         for stake_a in block_a.stakes.iter() {
-            stake::put(stake_a, db)?;
+            stake::put(&mut batch, stake_a, db)?;
+            inclusion::put(&mut batch, &stake_a.hash, &block_a.hash, db)?;
         }
-        db.put_cf(super::blocks(db), &block_a.hash, bincode::serialize(&block_a.b().c())?)?;
+        for evidence in block_a.evidence.iter() {
+            evidence::put(&mut batch, evidence, db)?;
+        }
+        batch.put_cf(super::blocks(db), block_a.hash, bincode::serialize(&block_a.b().c())?);
+        db.write(batch)?;
         Ok(())
     }
     pub fn get_a(db: &DBWithThreadMode<SingleThreaded>, hash: &[u8]) -> Result<BlockA, Box<dyn Error>> {
@@ -67,15 +123,13 @@ pub mod block {
         for hash in block_c.stake_hashes.iter() {
             stakes.push(stake::get_a(db, hash)?);
         }
-        let beta = match beta::get(db, hash) {
-            Ok(x) => Some(x),
-            Err(_) => None,
-        };
-        let input_public_key = match input_public_key::get(db, hash) {
-            Ok(x) => Some(x),
-            Err(_) => None,
-        };
-        let block_a = block_c.a(transactions, stakes, beta, input_public_key)?;
+        let mut evidence = vec![];
+        for hash in block_c.evidence_hashes.iter() {
+            evidence.push(evidence::get(db, hash)?);
+        }
+        let beta = beta::get(db, hash).ok();
+        let input_public_key = input_public_key::get(db, hash).ok();
+        let block_a = block_c.a(transactions, stakes, evidence, beta, input_public_key)?;
         if beta.is_none() {
             beta::put(hash, &block_a.beta, db)?;
         }
@@ -94,30 +148,73 @@ pub mod block {
         for hash in block_c.stake_hashes.iter() {
             stakes.push(stake::get_b(db, hash)?);
         }
-        Ok(block_c.b(transactions, stakes))
+        let mut evidence = vec![];
+        for hash in block_c.evidence_hashes.iter() {
+            evidence.push(evidence::get(db, hash)?);
+        }
+        Ok(block_c.b(transactions, stakes, evidence))
     }
     pub fn get_c(db: &DBWithThreadMode<SingleThreaded>, hash: &[u8]) -> Result<BlockC, Box<dyn Error>> {
         Ok(bincode::deserialize(&db.get_cf(super::blocks(db), hash)?.ok_or("block not found")?)?)
     }
+    // inclusions and evidence are kept, they reject replayed stakes and evidence and resolve pruned lookups
+    pub fn prune(db: &DBWithThreadMode<SingleThreaded>, hash: &[u8]) -> Result<(), Box<dyn Error>> {
+        let block_c = get_c(db, hash)?;
+        let mut batch = WriteBatch::default();
+        for hash in block_c.transaction_hashes.iter() {
+            batch.delete_cf(super::transactions(db), hash);
+            batch.delete_cf(super::input_addresses(db), hash);
+        }
+        for hash in block_c.stake_hashes.iter() {
+            batch.delete_cf(super::stakes(db), hash);
+            batch.delete_cf(super::input_addresses(db), hash);
+        }
+        batch.delete_cf(super::betas(db), hash);
+        batch.delete_cf(super::input_public_keys(db), hash);
+        db.write(batch)?;
+        Ok(())
+    }
     #[test]
     fn test_serialize_len() {
-        assert_eq!(197, bincode::serialize(&BlockC::default()).unwrap().len());
+        assert_eq!(209, bincode::serialize(&BlockC::default()).unwrap().len());
+    }
+    #[test]
+    fn test_prune() {
+        let tempdir = tempdir::TempDir::new("peacash-db").unwrap();
+        let db = super::open(tempdir.path().to_str().unwrap());
+        let mut transaction_a = pea_transaction::TransactionA::default();
+        transaction_a.hash = transaction_a.hash();
+        let hash = transaction_a.hash;
+        let evidence_0 = pea_block::Evidence {
+            block_c_0: BlockC::default(),
+            block_c_1: BlockC::default(),
+        };
+        let block_a = BlockA {
+            hash: [1; 32],
+            transactions: vec![transaction_a],
+            evidence: vec![evidence_0.clone()],
+            ..BlockA::default()
+        };
+        put(&block_a, &db).unwrap();
+        assert!(transaction::get_b(&db, &hash).is_ok());
+        prune(&db, &[1; 32]).unwrap();
+        assert!(transaction::get_b(&db, &hash).is_err());
+        assert!(get_c(&db, &[1; 32]).is_ok());
+        assert_eq!(inclusion::get(&db, &hash).unwrap(), vec![[1; 32]]);
+        assert!(evidence::get(&db, &evidence_0.hash()).is_ok());
     }
 }
 pub mod transaction {
     use super::input_address;
     use pea_transaction::{TransactionA, TransactionB};
-    use rocksdb::{DBWithThreadMode, SingleThreaded};
+    use rocksdb::{DBWithThreadMode, SingleThreaded, WriteBatch};
     use std::error::Error;
-    pub fn put(transaction_a: &TransactionA, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
-        db.put_cf(super::transactions(db), transaction_a.hash, bincode::serialize(&transaction_a.b())?)?;
+    pub fn put(batch: &mut WriteBatch, transaction_a: &TransactionA, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
+        batch.put_cf(super::transactions(db), transaction_a.hash, bincode::serialize(&transaction_a.b())?);
         Ok(())
     }
     pub fn get_a(db: &DBWithThreadMode<SingleThreaded>, hash: &[u8]) -> Result<TransactionA, Box<dyn Error>> {
-        let input_address = match input_address::get(db, hash) {
-            Ok(x) => Some(x),
-            Err(_) => None,
-        };
+        let input_address = input_address::get(db, hash).ok();
         let transaction_a = get_b(db, hash)?.a(input_address)?;
         if input_address.is_none() {
             input_address::put(hash, &transaction_a.input_address, db)?;
@@ -130,23 +227,20 @@ pub mod transaction {
     }
     #[test]
     fn test_serialize_len() {
-        assert_eq!(96, bincode::serialize(&TransactionB::default()).unwrap().len());
+        assert_eq!(102, bincode::serialize(&TransactionB::default()).unwrap().len());
     }
 }
 pub mod stake {
     use super::input_address;
     use pea_stake::{StakeA, StakeB};
-    use rocksdb::{DBWithThreadMode, SingleThreaded};
+    use rocksdb::{DBWithThreadMode, SingleThreaded, WriteBatch};
     use std::error::Error;
-    pub fn put(stake_a: &StakeA, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
-        db.put_cf(super::stakes(db), stake_a.hash, bincode::serialize(&stake_a.b())?)?;
+    pub fn put(batch: &mut WriteBatch, stake_a: &StakeA, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
+        batch.put_cf(super::stakes(db), stake_a.hash, bincode::serialize(&stake_a.b())?);
         Ok(())
     }
     pub fn get_a(db: &DBWithThreadMode<SingleThreaded>, hash: &[u8]) -> Result<StakeA, Box<dyn Error>> {
-        let input_address = match input_address::get(db, hash) {
-            Ok(x) => Some(x),
-            Err(_) => None,
-        };
+        let input_address = input_address::get(db, hash).ok();
         let stake_a = get_b(db, hash)?.a(input_address)?;
         if input_address.is_none() {
             input_address::put(hash, &stake_a.input_address, db)?;
@@ -159,15 +253,521 @@ pub mod stake {
     }
     #[test]
     fn test_serialize_len() {
-        assert_eq!(73, bincode::serialize(&StakeB::default()).unwrap().len());
+        assert_eq!(84, bincode::serialize(&StakeB::default()).unwrap().len());
+    }
+}
+pub mod evidence {
+    use pea_block::Evidence;
+    use rocksdb::{DBWithThreadMode, SingleThreaded, WriteBatch};
+    use std::error::Error;
+    pub fn put(batch: &mut WriteBatch, evidence: &Evidence, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
+        batch.put_cf(super::evidence(db), evidence.hash(), bincode::serialize(evidence)?);
+        Ok(())
+    }
+    pub fn get(db: &DBWithThreadMode<SingleThreaded>, hash: &[u8]) -> Result<Evidence, Box<dyn Error>> {
+        Ok(bincode::deserialize(&db.get_cf(super::evidence(db), hash)?.ok_or("evidence not found")?)?)
+    }
+}
+pub mod inclusion {
+    use pea_core::*;
+    use rocksdb::{DBWithThreadMode, SingleThreaded, WriteBatch};
+    use std::error::Error;
+    pub fn put(batch: &mut WriteBatch, hash: &[u8], block_hash: &Hash, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
+        let mut block_hashes = get(db, hash)?;
+        if !block_hashes.contains(block_hash) {
+            block_hashes.push(*block_hash);
+            batch.put_cf(super::inclusions(db), hash, bincode::serialize(&block_hashes)?);
+        }
+        Ok(())
+    }
+    pub fn get(db: &DBWithThreadMode<SingleThreaded>, hash: &[u8]) -> Result<Vec<Hash>, Box<dyn Error>> {
+        match db.get_cf(super::inclusions(db), hash)? {
+            Some(bytes) => Ok(bincode::deserialize(&bytes)?),
+            None => Ok(vec![]),
+        }
+    }
+}
+pub mod history {
+    use pea_block::BlockA;
+    use pea_core::*;
+    use pea_transaction::Htlc;
+    use rocksdb::{DBWithThreadMode, Direction as IteratorDirection, IteratorMode, SingleThreaded, WriteBatch};
+    use std::error::Error;
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Direction {
+        In,
+        Out,
+        Stake,
+    }
+    #[derive(Debug, Clone)]
+    pub struct Entry {
+        pub hash: Hash,
+        pub block_hash: Hash,
+        pub height: usize,
+        pub direction: Direction,
+    }
+    fn entry(address: &AddressBytes, height: usize, hash: &Hash, block_hash: &Hash, direction: Direction) -> (Vec<u8>, Vec<u8>) {
+        let key = [&address[..], &(height as u64).to_be_bytes(), hash].concat();
+        let direction = match direction {
+            Direction::In => 0,
+            Direction::Out => 1,
+            Direction::Stake => 2,
+        };
+        (key, [&block_hash[..], &[direction]].concat())
+    }
+    pub fn entries(block_a: &BlockA, height: usize) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut vec = vec![];
+        for transaction_a in block_a.transactions.iter() {
+            match transaction_a.htlc {
+                Some(Htlc::Claim { .. }) | Some(Htlc::Refund { .. }) => {
+                    vec.push(entry(&transaction_a.input_address, height, &transaction_a.hash, &block_a.hash, Direction::In));
+                }
+                Some(Htlc::Lock { .. }) => {
+                    vec.push(entry(&transaction_a.input_address, height, &transaction_a.hash, &block_a.hash, Direction::Out));
+                }
+                None => {
+                    vec.push(entry(&transaction_a.input_address, height, &transaction_a.hash, &block_a.hash, Direction::Out));
+                    for (output_address, _) in transaction_a.outputs.iter() {
+                        vec.push(entry(output_address, height, &transaction_a.hash, &block_a.hash, Direction::In));
+                    }
+                }
+            }
+        }
+        for stake_a in block_a.stakes.iter() {
+            vec.push(entry(&stake_a.input_address, height, &stake_a.hash, &block_a.hash, Direction::Stake));
+        }
+        vec
+    }
+    pub fn put(batch: &mut WriteBatch, block_a: &BlockA, height: usize, db: &DBWithThreadMode<SingleThreaded>) {
+        for (key, value) in entries(block_a, height) {
+            batch.put_cf(super::history(db), key, value);
+        }
+    }
+    pub fn delete(batch: &mut WriteBatch, block_a: &BlockA, height: usize, db: &DBWithThreadMode<SingleThreaded>) {
+        for (key, _) in entries(block_a, height) {
+            batch.delete_cf(super::history(db), key);
+        }
+    }
+    pub fn get(db: &DBWithThreadMode<SingleThreaded>, address: &AddressBytes) -> Result<Vec<Entry>, Box<dyn Error>> {
+        let mut vec = vec![];
+        for res in db.iterator_cf(super::history(db), IteratorMode::From(address, IteratorDirection::Forward)) {
+            let (key, value) = res?;
+            if !key.starts_with(address) {
+                break;
+            }
+            vec.push(Entry {
+                height: u64::from_be_bytes(key[20..28].try_into()?) as usize,
+                hash: key[28..60].try_into()?,
+                block_hash: value[..32].try_into()?,
+                direction: match value[32] {
+                    0 => Direction::In,
+                    1 => Direction::Out,
+                    _ => Direction::Stake,
+                },
+            });
+        }
+        Ok(vec)
+    }
+}
+pub mod height {
+    use super::{block, history};
+    use pea_core::*;
+    use pea_tree::Tree;
+    use rocksdb::{DBWithThreadMode, Direction, IteratorMode, SingleThreaded, WriteBatch};
+    use std::error::Error;
+    pub fn put(batch: &mut WriteBatch, hash: &Hash, height: usize, db: &DBWithThreadMode<SingleThreaded>) {
+        batch.put_cf(super::block_heights(db), hash, (height as u64).to_be_bytes());
+    }
+    pub fn get(db: &DBWithThreadMode<SingleThreaded>, hash: &[u8]) -> Result<usize, Box<dyn Error>> {
+        let bytes = db.get_cf(super::block_heights(db), hash)?.ok_or("block height not found")?;
+        Ok(u64::from_be_bytes(bytes.as_slice().try_into()?) as usize)
+    }
+    pub fn next(db: &DBWithThreadMode<SingleThreaded>, previous_hash: &Hash) -> Result<usize, Box<dyn Error>> {
+        if previous_hash == &[0; 32] {
+            return Ok(0);
+        }
+        Ok(get(db, previous_hash)? + 1)
+    }
+    pub fn hash(db: &DBWithThreadMode<SingleThreaded>, height: usize) -> Result<Option<Hash>, Box<dyn Error>> {
+        match db.get_cf(super::heights(db), (height as u64).to_be_bytes())? {
+            Some(bytes) => Ok(Some(bytes.as_slice().try_into()?)),
+            None => Ok(None),
+        }
+    }
+    pub fn update(tree: &Tree, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
+        let mut batch = WriteBatch::default();
+        let main = tree.main().map(|x| (x.0, x.1));
+        let start = main.map(|x| x.1 as u64 + 1).unwrap_or(0);
+        for res in db.iterator_cf(super::heights(db), IteratorMode::From(&start.to_be_bytes(), Direction::Forward)) {
+            let (key, value) = res?;
+            batch.delete_cf(super::heights(db), &key);
+            if let Ok(block_a) = block::get_a(db, &value) {
+                history::delete(&mut batch, &block_a, u64::from_be_bytes(key[..].try_into()?) as usize, db);
+            }
+        }
+        if let Some((mut hash, mut height)) = main {
+            loop {
+                let previous = self::hash(db, height)?;
+                if previous == Some(hash) {
+                    break;
+                }
+                if let Some(Ok(block_a)) = previous.map(|x| block::get_a(db, &x)) {
+                    history::delete(&mut batch, &block_a, height, db);
+                }
+                if let Ok(block_a) = block::get_a(db, &hash) {
+                    history::put(&mut batch, &block_a, height, db);
+                }
+                batch.put_cf(super::heights(db), (height as u64).to_be_bytes(), hash);
+                if height == 0 {
+                    break;
+                }
+                hash = *tree.get(&hash).ok_or("broken chain")?;
+                height -= 1;
+            }
+        }
+        db.write(batch)?;
+        Ok(())
+    }
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use tempdir::TempDir;
+        #[test]
+        fn test_update() {
+            let tempdir = TempDir::new("peacash-db").unwrap();
+            let db = super::super::open(tempdir.path().to_str().unwrap());
+            let mut tree = Tree::default();
+            for (hash, previous_hash, timestamp) in [([1; 32], [0; 32], 0), ([2; 32], [1; 32], 0), ([3; 32], [1; 32], 1)] {
+                tree.insert(hash, previous_hash, timestamp);
+                let mut batch = WriteBatch::default();
+                put(&mut batch, &hash, next(&db, &previous_hash).unwrap(), &db);
+                db.write(batch).unwrap();
+            }
+            tree.sort_branches();
+            update(&tree, &db).unwrap();
+            assert_eq!(hash(&db, 1).unwrap(), Some([2; 32]));
+            tree.insert([4; 32], [3; 32], 2);
+            tree.sort_branches();
+            update(&tree, &db).unwrap();
+            assert_eq!(get(&db, &[3; 32]).unwrap(), 1);
+            assert_eq!(hash(&db, 1).unwrap(), Some([3; 32]));
+            assert_eq!(hash(&db, 2).unwrap(), Some([4; 32]));
+            tree.clear();
+            update(&tree, &db).unwrap();
+            assert_eq!(hash(&db, 0).unwrap(), None);
+        }
+    }
+}
+pub mod pruned {
+    use rocksdb::{DBWithThreadMode, SingleThreaded};
+    use std::error::Error;
+    pub fn put(height: usize, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
+        db.put_cf(super::pruned(db), "height", (height as u64).to_be_bytes())?;
+        Ok(())
+    }
+    pub fn get(db: &DBWithThreadMode<SingleThreaded>) -> Result<usize, Box<dyn Error>> {
+        match db.get_cf(super::pruned(db), "height")? {
+            Some(bytes) => Ok(u64::from_be_bytes(bytes.as_slice().try_into()?) as usize),
+            None => Ok(0),
+        }
+    }
+}
+pub mod finalized {
+    use pea_core::*;
+    use rocksdb::{DBWithThreadMode, SingleThreaded};
+    use std::error::Error;
+    pub fn put(hash: &Hash, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
+        db.put_cf(super::finalized(db), "hash", hash)?;
+        Ok(())
+    }
+    pub fn get(db: &DBWithThreadMode<SingleThreaded>) -> Result<Option<Hash>, Box<dyn Error>> {
+        match db.get_cf(super::finalized(db), "hash")? {
+            Some(bytes) => Ok(Some(bytes.as_slice().try_into()?)),
+            None => Ok(None),
+        }
+    }
+}
+pub mod snapshot {
+    use pea_core::*;
+    use rocksdb::{DBWithThreadMode, IteratorMode, SingleThreaded, WriteBatch};
+    use std::error::Error;
+    pub type Snapshot = (usize, Hash, Vec<u8>);
+    pub fn put(height: usize, hash: &Hash, bytes: &[u8], keep: usize, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
+        let mut batch = WriteBatch::default();
+        batch.put_cf(super::snapshots(db), [&(height as u64).to_be_bytes()[..], hash].concat(), bytes);
+        for res in db.iterator_cf(super::snapshots(db), IteratorMode::End).skip(keep.saturating_sub(1)) {
+            let (key, _) = res?;
+            batch.delete_cf(super::snapshots(db), key);
+        }
+        db.write(batch)?;
+        Ok(())
+    }
+    pub fn get(db: &DBWithThreadMode<SingleThreaded>) -> Result<Vec<Snapshot>, Box<dyn Error>> {
+        let mut vec = vec![];
+        for res in db.iterator_cf(super::snapshots(db), IteratorMode::End) {
+            let (key, value) = res?;
+            vec.push((u64::from_be_bytes(key[..8].try_into()?) as usize, key[8..40].try_into()?, value.to_vec()));
+        }
+        Ok(vec)
+    }
+}
+pub mod consistency {
+    use super::{block, height, history, inclusion, pruned, tree};
+    use pea_block::{BlockC, Evidence};
+    use pea_core::*;
+    use pea_stake::StakeB;
+    use pea_transaction::TransactionB;
+    use pea_tree::Tree;
+    use rocksdb::{ColumnFamily, DBWithThreadMode, IteratorMode, SingleThreaded, WriteBatch};
+    use std::{
+        collections::{HashMap, HashSet},
+        error::Error,
+    };
+    fn keys(db: &DBWithThreadMode<SingleThreaded>, cf: &ColumnFamily) -> Result<HashSet<Vec<u8>>, Box<dyn Error>> {
+        let mut set = HashSet::new();
+        for res in db.iterator_cf(cf, IteratorMode::Start) {
+            let (key, _) = res?;
+            set.insert(key.to_vec());
+        }
+        Ok(set)
+    }
+    fn is_pruned(db: &DBWithThreadMode<SingleThreaded>, key: &[u8], value: &[u8], pruned: usize) -> Result<bool, Box<dyn Error>> {
+        let height = u64::from_be_bytes(key[20..28].try_into()?) as usize;
+        Ok(height < pruned && height::hash(db, height)?.is_some_and(|x| x[..] == value[..32]))
+    }
+    pub fn verify(db: &DBWithThreadMode<SingleThreaded>) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut errors = vec![];
+        let pruned = pruned::get(db)?;
+        let mut blocks: HashMap<Vec<u8>, BlockC> = HashMap::new();
+        for res in db.iterator_cf(super::blocks(db), IteratorMode::Start) {
+            let (key, bytes) = res?;
+            match bincode::deserialize::<BlockC>(&bytes) {
+                Ok(block_c) if block_c.hash()[..] == key[..] => {
+                    blocks.insert(key.to_vec(), block_c);
+                }
+                Ok(_) => errors.push(format!("block {} hash mismatch", hex::encode(&key))),
+                Err(_) => errors.push(format!("block {} corrupt", hex::encode(&key))),
+            }
+        }
+        let transactions = keys(db, super::transactions(db))?;
+        let stakes = keys(db, super::stakes(db))?;
+        let evidence = keys(db, super::evidence(db))?;
+        for (hash, block_c) in blocks.iter() {
+            if block_c.previous_hash != [0; 32] && !blocks.contains_key(&block_c.previous_hash[..]) {
+                errors.push(format!("block {} previous hash missing", hex::encode(hash)));
+                continue;
+            }
+            let height = match height::get(db, hash) {
+                Ok(x) => x,
+                Err(_) => {
+                    errors.push(format!("block {} height missing", hex::encode(hash)));
+                    continue;
+                }
+            };
+            if height::next(db, &block_c.previous_hash).ok() != Some(height) {
+                errors.push(format!("block {} height mismatch", hex::encode(hash)));
+            }
+            if height < pruned && height::hash(db, height)?.is_some_and(|x| x[..] == hash[..]) {
+                continue;
+            }
+            for x in block_c.transaction_hashes.iter().filter(|x| !transactions.contains(&x[..])) {
+                errors.push(format!("block {} transaction {} missing", hex::encode(hash), hex::encode(x)));
+            }
+            for x in block_c.stake_hashes.iter().filter(|x| !stakes.contains(&x[..])) {
+                errors.push(format!("block {} stake {} missing", hex::encode(hash), hex::encode(x)));
+            }
+            for x in block_c.evidence_hashes.iter().filter(|x| !evidence.contains(&x[..])) {
+                errors.push(format!("block {} evidence {} missing", hex::encode(hash), hex::encode(x)));
+            }
+        }
+        for res in db.iterator_cf(super::transactions(db), IteratorMode::Start) {
+            let (key, bytes) = res?;
+            match bincode::deserialize::<TransactionB>(&bytes) {
+                Ok(transaction_b) if transaction_b.hash()[..] == key[..] => {}
+                Ok(_) => errors.push(format!("transaction {} hash mismatch", hex::encode(&key))),
+                Err(_) => errors.push(format!("transaction {} corrupt", hex::encode(&key))),
+            }
+            if !inclusion::get(db, &key)?.iter().any(|x| blocks.contains_key(&x[..])) {
+                errors.push(format!("transaction {} not in any block", hex::encode(&key)));
+            }
+        }
+        for res in db.iterator_cf(super::stakes(db), IteratorMode::Start) {
+            let (key, bytes) = res?;
+            match bincode::deserialize::<StakeB>(&bytes) {
+                Ok(stake_b) if stake_b.hash()[..] == key[..] => {}
+                Ok(_) => errors.push(format!("stake {} hash mismatch", hex::encode(&key))),
+                Err(_) => errors.push(format!("stake {} corrupt", hex::encode(&key))),
+            }
+            if !inclusion::get(db, &key)?.iter().any(|x| blocks.contains_key(&x[..])) {
+                errors.push(format!("stake {} not in any block", hex::encode(&key)));
+            }
+        }
+        for res in db.iterator_cf(super::evidence(db), IteratorMode::Start) {
+            let (key, bytes) = res?;
+            match bincode::deserialize::<Evidence>(&bytes) {
+                Ok(evidence) if evidence.hash()[..] == key[..] => {}
+                Ok(_) => errors.push(format!("evidence {} hash mismatch", hex::encode(&key))),
+                Err(_) => errors.push(format!("evidence {} corrupt", hex::encode(&key))),
+            }
+        }
+        for key in keys(db, super::input_addresses(db))? {
+            if !transactions.contains(&key) && !stakes.contains(&key) {
+                errors.push(format!("input address {} orphaned", hex::encode(&key)));
+            }
+        }
+        for (name, cf) in [("input public key", super::input_public_keys(db)), ("beta", super::betas(db))] {
+            for key in keys(db, cf)? {
+                if !blocks.contains_key(&key) {
+                    errors.push(format!("{} {} orphaned", name, hex::encode(&key)));
+                }
+            }
+        }
+        for res in db.iterator_cf(super::heights(db), IteratorMode::Start) {
+            let (key, value) = res?;
+            if !blocks.contains_key(&value[..]) || height::get(db, &value).ok() != Some(u64::from_be_bytes(key[..].try_into()?) as usize) {
+                errors.push(format!("height {} points to {}", hex::encode(&key), hex::encode(&value)));
+            }
+        }
+        let mut entries = HashMap::new();
+        for res in db.iterator_cf(super::heights(db), IteratorMode::Start) {
+            let (key, value) = res?;
+            if let Ok(block_a) = block::get_a(db, &value) {
+                entries.extend(history::entries(&block_a, u64::from_be_bytes(key[..].try_into()?) as usize));
+            }
+        }
+        for res in db.iterator_cf(super::history(db), IteratorMode::Start) {
+            let (key, value) = res?;
+            match entries.remove(&key[..]) {
+                Some(x) if x[..] == value[..] => {}
+                Some(_) => errors.push(format!("history {} mismatch", hex::encode(&key))),
+                None if is_pruned(db, &key, &value, pruned)? => {}
+                None => errors.push(format!("history {} not on main chain", hex::encode(&key))),
+            }
+        }
+        for key in entries.keys() {
+            errors.push(format!("history {} missing", hex::encode(key)));
+        }
+        Ok(errors)
+    }
+    pub fn reindex(db: &DBWithThreadMode<SingleThreaded>) -> Result<usize, Box<dyn Error>> {
+        let mut batch = WriteBatch::default();
+        for res in db.iterator_cf(super::history(db), IteratorMode::Start) {
+            let (key, value) = res?;
+            if block::get_c(db, &value[..32]).is_err() || block::get_a(db, &value[..32]).is_ok() {
+                batch.delete_cf(super::history(db), key);
+            }
+        }
+        for cf in [
+            super::input_addresses(db),
+            super::input_public_keys(db),
+            super::betas(db),
+            super::inclusions(db),
+            super::heights(db),
+            super::block_heights(db),
+        ] {
+            super::clear(&mut batch, db, cf)?;
+        }
+        db.write(batch)?;
+        let mut tree = Tree::default();
+        tree::reload(&mut tree, db);
+        height::update(&tree, db)?;
+        let mut blocks = 0;
+        for res in db.iterator_cf(super::blocks(db), IteratorMode::Start) {
+            let (hash, _) = res?;
+            let block_c = block::get_c(db, &hash)?;
+            let block_hash: Hash = hash[..].try_into()?;
+            let mut batch = WriteBatch::default();
+            for x in block_c.transaction_hashes.iter().chain(block_c.stake_hashes.iter()) {
+                inclusion::put(&mut batch, x, &block_hash, db)?;
+            }
+            db.write(batch)?;
+            let _ = block::get_a(db, &hash);
+            blocks += 1;
+        }
+        Ok(blocks)
+    }
+}
+pub mod pending {
+    use pea_stake::{StakeA, StakeB};
+    use pea_transaction::{TransactionA, TransactionB};
+    use rocksdb::{DBWithThreadMode, IteratorMode, SingleThreaded, WriteBatch};
+    use std::error::Error;
+    pub type Pending = (Vec<(TransactionA, u32)>, Vec<(StakeA, u32)>);
+    pub fn put(transactions: &[(TransactionA, u32)], stakes: &[(StakeA, u32)], db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
+        let mut batch = WriteBatch::default();
+        super::clear(&mut batch, db, super::pending_transactions(db))?;
+        super::clear(&mut batch, db, super::pending_stakes(db))?;
+        for (transaction_a, received) in transactions.iter() {
+            batch.put_cf(
+                super::pending_transactions(db),
+                transaction_a.hash,
+                bincode::serialize(&(transaction_a.b(), received))?,
+            );
+        }
+        for (stake_a, received) in stakes.iter() {
+            batch.put_cf(super::pending_stakes(db), stake_a.hash, bincode::serialize(&(stake_a.b(), received))?);
+        }
+        db.write(batch)?;
+        Ok(())
+    }
+    pub fn get(db: &DBWithThreadMode<SingleThreaded>) -> Result<Pending, Box<dyn Error>> {
+        let mut transactions = vec![];
+        for res in db.iterator_cf(super::pending_transactions(db), IteratorMode::Start) {
+            let (_, bytes) = res?;
+            let (transaction_b, received): (TransactionB, u32) = bincode::deserialize(&bytes)?;
+            transactions.push((transaction_b.a(None)?, received));
+        }
+        let mut stakes = vec![];
+        for res in db.iterator_cf(super::pending_stakes(db), IteratorMode::Start) {
+            let (_, bytes) = res?;
+            let (stake_b, received): (StakeB, u32) = bincode::deserialize(&bytes)?;
+            stakes.push((stake_b.a(None)?, received));
+        }
+        Ok((transactions, stakes))
     }
 }
 pub mod tree {
     use pea_block::BlockC;
     use pea_core::*;
     use pea_tree::Tree;
-    use rocksdb::{DBWithThreadMode, IteratorMode, SingleThreaded};
-    use std::collections::HashMap;
+    use rocksdb::{DBWithThreadMode, IteratorMode, SingleThreaded, WriteBatch};
+    use std::{collections::HashMap, error::Error};
+    pub fn restore(tree: &mut Tree, db: &DBWithThreadMode<SingleThreaded>, trust_fork_after_blocks: usize) -> Result<(), Box<dyn Error>> {
+        tree.clear();
+        let mut hashes: Vec<Hash> = vec![];
+        for res in db.iterator_cf(super::heights(db), IteratorMode::Start) {
+            let (key, value) = res?;
+            if u64::from_be_bytes(key[..].try_into()?) as usize != hashes.len() {
+                return Err("heights index gap".into());
+            }
+            hashes.push(value[..].try_into()?);
+        }
+        let latest = super::block::get_c(db, hashes.last().ok_or("heights index empty")?)?;
+        let mut previous_hash = [0; 32];
+        for (height, hash) in hashes.iter().enumerate() {
+            let timestamp = if height == hashes.len() - 1 { latest.timestamp } else { 0 };
+            tree.insert(*hash, previous_hash, timestamp);
+            previous_hash = *hash;
+        }
+        let start = hashes.len().saturating_sub(trust_fork_after_blocks + 1);
+        let mut forks = vec![];
+        for res in db.iterator_cf(super::block_heights(db), IteratorMode::Start) {
+            let (hash, bytes) = res?;
+            let height = u64::from_be_bytes(bytes[..].try_into()?) as usize;
+            if height >= start && tree.get(&hash[..].try_into()?).is_none() {
+                forks.push((height, hash));
+            }
+        }
+        forks.sort();
+        for (_, hash) in forks {
+            let block_c = super::block::get_c(db, &hash)?;
+            if block_c.previous_hash == [0; 32] || tree.get(&block_c.previous_hash).is_some() {
+                tree.insert(hash[..].try_into()?, block_c.previous_hash, block_c.timestamp);
+            }
+        }
+        tree.sort_branches();
+        Ok(())
+    }
     pub fn reload(tree: &mut Tree, db: &DBWithThreadMode<SingleThreaded>) {
         tree.clear();
         let mut map: HashMap<Hash, Vec<(Hash, u32)>> = HashMap::new();
@@ -196,7 +796,13 @@ pub mod tree {
             hashes_0.push((*hash, *timestamp));
         }
         let mut vec = vec![];
+        let mut heights = vec![];
+        let mut height = 0;
         loop {
+            for (hash, _) in hashes_0.iter() {
+                heights.push((*hash, height));
+            }
+            height += 1;
             let mut hashes_1 = vec![];
             for previous_hash in previous_hashes.clone() {
                 for (hash, timestamp) in hashes_0.clone() {
@@ -221,13 +827,20 @@ pub mod tree {
             tree.insert(hash, previous_hash, timestamp);
         }
         tree.sort_branches();
+        if tree.main().is_some_and(|main| super::height::get(db, &main.0).is_err()) {
+            let mut batch = WriteBatch::default();
+            for (hash, height) in heights {
+                batch.put_cf(super::block_heights(db), hash, (height as u64).to_be_bytes());
+            }
+            db.write(batch).unwrap();
+        }
     }
 }
 pub mod peer {
     use rocksdb::{DBWithThreadMode, IteratorMode, SingleThreaded};
     use std::error::Error;
     pub fn put(peer: &str, db: &DBWithThreadMode<SingleThreaded>) -> Result<(), Box<dyn Error>> {
-        db.put_cf(super::peers(db), peer, &[])?;
+        db.put_cf(super::peers(db), peer, [])?;
         Ok(())
     }
     pub fn get_all(db: &DBWithThreadMode<SingleThreaded>) -> Vec<String> {
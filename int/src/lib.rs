@@ -52,7 +52,7 @@ pub fn to_string(uint: u128) -> String {
         s.push_str(&string);
         string = s;
     }
-    if string == "" {
+    if string.is_empty() {
         string.push('0');
     }
     string
@@ -61,11 +61,39 @@ impl Key {
         let public_key_bytes: PublicKeyBytes = SECP256K1.recover_ecdsa(&message, &signature)?.serialize();
         Ok(public_key_bytes)
     }
+    pub fn multisig_address(threshold: u8, addresses: &[AddressBytes]) -> AddressBytes {
+        let mut hasher = Sha256::new();
+        hasher.update([threshold]);
+        for address in addresses {
+            hasher.update(address);
+        }
+        let hash = hasher.finalize();
+        let mut address = [0; 20];
+        address.copy_from_slice(&hash[..20]);
+        address
+    }
+    pub fn multisig_verify(hash: &Hash, threshold: u8, addresses: &[AddressBytes], signatures: &[SignatureBytes]) -> Result<(), Box<dyn Error>> {
+        let mut signers = vec![];
+        for signature_bytes in signatures {
+            let address = Key::address(&Key::recover(hash, signature_bytes)?);
+            if !addresses.contains(&address) {
+                return Err("multisig signer not in policy".into());
+            }
+            if signers.contains(&address) {
+                return Err("multisig duplicate signer".into());
+            }
+            signers.push(address);
+        }
+        if signers.len() < threshold as usize {
+            return Err("multisig threshold not reached".into());
+        }
+        Ok(())
+    }
     #[cfg(feature = "vrf")]
     pub fn vrf_prove(&self, alpha: &[u8]) -> Option<Pi> {
         let mut vrf = ECVRF::from_suite(CipherSuite::SECP256K1_SHA256_TAI).unwrap();
         let pi = vrf.prove(&self.secret_key_bytes(), alpha);
-        if let Err(_) = pi {
+        if pi.is_err() {
             return None;
         }
         Some(pi.unwrap().try_into().unwrap())
@@ -74,7 +102,7 @@ impl Key {
     pub fn vrf_proof_to_hash(pi: &[u8]) -> Option<Beta> {
         let mut vrf = ECVRF::from_suite(CipherSuite::SECP256K1_SHA256_TAI).unwrap();
         let beta = vrf.proof_to_hash(pi);
-        if let Err(_) = beta {
+        if beta.is_err() {
             return None;
         }
         Some(beta.unwrap().try_into().unwrap())
@@ -83,7 +111,7 @@ impl Key {
     pub fn vrf_verify(y: &[u8], pi: &[u8], alpha: &[u8]) -> Option<Beta> {
         let mut vrf = ECVRF::from_suite(CipherSuite::SECP256K1_SHA256_TAI).unwrap();
         let beta = vrf.verify(y, pi, alpha);
-        if let Err(_) = beta {
+        if beta.is_err() {
             return None;
         }
         Some(beta.unwrap().try_into().unwrap())
@@ -107,6 +135,18 @@ mod tests {
         assert_eq!(key.public_key_bytes(), Key::recover(&hash, &signature_bytes).unwrap());
     }
     #[test]
+    fn test_multisig_verify() {
+        let keys = [Key::generate(), Key::generate(), Key::generate()];
+        let mut addresses = keys.iter().map(|x| x.address_bytes()).collect::<Vec<_>>();
+        addresses.sort();
+        let hash = [0; 32];
+        let signatures = [keys[0].sign(&hash).unwrap(), keys[2].sign(&hash).unwrap()];
+        assert!(Key::multisig_verify(&hash, 2, &addresses, &signatures).is_ok());
+        assert!(Key::multisig_verify(&hash, 3, &addresses, &signatures).is_err());
+        assert!(Key::multisig_verify(&hash, 2, &addresses, &[signatures[0], keys[0].sign(&hash).unwrap()]).is_err());
+        assert!(Key::multisig_verify(&hash, 1, &addresses, &[Key::generate().sign(&hash).unwrap()]).is_err());
+    }
+    #[test]
     #[cfg(feature = "vrf")]
     fn test_vrf_public_key() {
         let mut vrf = ECVRF::from_suite(CipherSuite::SECP256K1_SHA256_TAI).unwrap();
@@ -4,7 +4,6 @@ use libp2p::{
     identify, identity, mdns, ping,
     swarm::NetworkBehaviour,
 };
-use pea_core::*;
 use std::error::Error;
 #[derive(NetworkBehaviour)]
 #[behaviour(out_event = "OutEvent")]
@@ -15,10 +14,10 @@ pub struct Behaviour {
     pub autonat: autonat::Behaviour,
 }
 impl Behaviour {
-    pub async fn new(local_key: identity::Keypair) -> Result<Self, Box<dyn Error>> {
+    pub async fn new(local_key: identity::Keypair, protocol_version: String) -> Result<Self, Box<dyn Error>> {
         Ok(Self {
             mdns: mdns::tokio::Behaviour::new(mdns::Config::default())?,
-            identify: identify::Behaviour::new(identify::Config::new(PROTOCOL_VERSION.to_string(), local_key.public())),
+            identify: identify::Behaviour::new(identify::Config::new(protocol_version, local_key.public())),
             gossipsub: Gossipsub::new(MessageAuthenticity::Signed(local_key.clone()), GossipsubConfigBuilder::default().build()?)?,
             autonat: autonat::Behaviour::new(local_key.public().to_peer_id(), autonat::Config::default()),
         })
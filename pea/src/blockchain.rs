@@ -1,58 +1,126 @@
-use crate::{state::Dynamic, states::States, sync::Sync};
+use crate::{
+    mempool::{Entry, Mempool, Status},
+    spec::ChainSpec,
+    state::{self, Dynamic, Trusted},
+    states::States,
+    sync::Sync,
+    util,
+};
 use colored::*;
 use log::{debug, info, warn};
-use pea_block::{BlockA, BlockB};
+use pea_block::{BlockA, BlockB, Checkpoint, Evidence};
 use pea_core::*;
 use pea_db as db;
 use pea_key::Key;
 use pea_stake::{StakeA, StakeB};
-use pea_transaction::{TransactionA, TransactionB};
+use pea_transaction::{Htlc, TransactionA, TransactionB};
 use pea_tree::Tree;
 use rocksdb::{DBWithThreadMode, SingleThreaded};
 use std::collections::HashMap;
-use std::{error::Error, time::Instant};
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+    time::Instant,
+};
 #[derive(Debug)]
 pub struct Blockchain {
     pub db: DBWithThreadMode<SingleThreaded>,
     pub key: Key,
     pub tree: Tree,
     pub states: States,
-    pub pending_transactions: Vec<TransactionA>,
-    pub pending_stakes: Vec<StakeA>,
+    pub mempool: Mempool,
+    pub pending_evidence: Vec<Evidence>,
     pub pending_blocks: Vec<BlockA>,
+    pub checkpoints: HashMap<Hash, HashMap<AddressBytes, Checkpoint>>,
+    pub finalized: Option<Hash>,
     pub sync: Sync,
     pub trust_fork_after_blocks: usize,
     pub pending_blocks_limit: usize,
     pub time_delta: u32,
+    pub prune: Option<usize>,
+    pub pruned: usize,
     pub offline: HashMap<AddressBytes, Hash>,
+    pub spec: ChainSpec,
 }
 impl Blockchain {
-    pub fn new(db: DBWithThreadMode<SingleThreaded>, key: Key, trust_fork_after_blocks: usize, pending_blocks_limit: usize, time_delta: u32) -> Self {
+    pub fn new(
+        db: DBWithThreadMode<SingleThreaded>,
+        key: Key,
+        trust_fork_after_blocks: usize,
+        pending_blocks_limit: usize,
+        time_delta: u32,
+        prune: Option<usize>,
+        spec: ChainSpec,
+    ) -> Self {
         Self {
             db,
             key,
             tree: Tree::default(),
-            states: States::default(),
-            pending_transactions: vec![],
-            pending_stakes: vec![],
+            states: States::new(&spec),
+            mempool: Mempool::default(),
+            pending_evidence: vec![],
             pending_blocks: vec![],
-            sync: Sync::default(),
+            checkpoints: HashMap::new(),
+            finalized: None,
+            sync: Sync::new(spec.block_time_min),
             trust_fork_after_blocks,
             pending_blocks_limit,
             time_delta,
+            prune,
+            pruned: 0,
             offline: HashMap::new(),
+            spec,
         }
     }
-    pub fn load(&mut self) {
+    pub fn load(&mut self) -> Result<(), Box<dyn Error>> {
         let start = Instant::now();
-        db::tree::reload(&mut self.tree, &self.db);
+        if let Err(err) = db::tree::restore(&mut self.tree, &self.db, self.trust_fork_after_blocks) {
+            debug!("Tree {}", err);
+            db::tree::reload(&mut self.tree, &self.db);
+        }
+        db::height::update(&self.tree, &self.db)?;
         self.sync.index = self.height().saturating_sub(self.trust_fork_after_blocks + SYNC_BLOCKS_PER_TICK);
         info!("Loaded tree in {}", format!("{:?}", start.elapsed()).yellow());
         let start = Instant::now();
         let (hashes_trusted, hashes_dynamic) = self.tree.hashes(self.trust_fork_after_blocks);
-        self.states.trusted.load(&self.db, &hashes_trusted);
-        self.states.dynamic = Dynamic::from(&self.db, &hashes_dynamic, &self.states.trusted);
+        let restored = match Trusted::restore(&self.db, &hashes_trusted) {
+            Ok(Some(trusted)) => {
+                info!("Restored snapshot at {}", trusted.hashes.len().to_string().yellow());
+                self.states.trusted = trusted;
+                self.states.trusted.hashes.len()
+            }
+            Ok(None) => 0,
+            Err(err) => {
+                warn!("Snapshot {}", err);
+                0
+            }
+        };
+        self.pruned = db::pruned::get(&self.db)?;
+        if restored < self.pruned {
+            return Err("pruned database without snapshot".into());
+        }
+        self.states.trusted.load(&self.db, &self.spec, &hashes_trusted[restored..]);
+        if self.spec.snapshot_interval != 0 && hashes_trusted.len() - restored >= self.spec.snapshot_interval {
+            self.states.trusted.snapshot(&self.db, &self.spec)?;
+        }
+        self.states.dynamic = Dynamic::from(&self.db, &self.spec, &hashes_dynamic, &self.states.trusted);
         info!("Loaded states in {}", format!("{:?}", start.elapsed()).yellow());
+        self.finalized = db::finalized::get(&self.db)?.filter(|x| self.states.trusted.hashes.contains(x) || self.states.dynamic.hashes.contains(x));
+        match db::pending::get(&self.db) {
+            Ok(pending) => {
+                self.mempool = Mempool::from(&self.spec, pending);
+                self.revalidate_mempool(util::timestamp());
+                info!(
+                    "Loaded mempool with {} transactions and {} stakes",
+                    self.mempool.transactions.len().to_string().yellow(),
+                    self.mempool.stakes.len().to_string().yellow()
+                );
+            }
+            Err(err) => warn!("Mempool {}", err),
+        }
+        Ok(())
     }
     pub fn height(&self) -> usize {
         if let Some(main) = self.tree.main() {
@@ -61,61 +129,167 @@ impl Blockchain {
             0
         }
     }
+    pub fn hash_at_height(&self, height: usize) -> Option<Hash> {
+        db::height::hash(&self.db, height).ok().flatten()
+    }
+    pub fn inclusion(&self, hash: &Hash) -> Option<(Hash, usize, usize)> {
+        for block_hash in db::inclusion::get(&self.db, hash).ok()? {
+            let height = match db::height::get(&self.db, &block_hash) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            if self.hash_at_height(height) == Some(block_hash) {
+                return Some((block_hash, height, self.height() - height + 1));
+            }
+        }
+        None
+    }
+    pub fn transaction_status(&self, hash: &Hash) -> Option<Status> {
+        match self.inclusion(hash) {
+            Some((block_hash, height, _)) => Some(Status::Included { block_hash, height }),
+            None => self.mempool.status(hash).cloned(),
+        }
+    }
+    pub fn history(&self, address: &AddressBytes, offset: usize, limit: usize) -> Result<Vec<db::history::Entry>, Box<dyn Error>> {
+        Ok(db::history::get(&self.db, address)?.into_iter().skip(offset).take(limit).collect())
+    }
+    pub fn export(&self, path: impl AsRef<Path>) -> Result<usize, Box<dyn Error>> {
+        if self.pruned > 0 {
+            return Err("pruned".into());
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        let mut height = 0;
+        while let Some(hash) = self.hash_at_height(height) {
+            let bytes = bincode::serialize(&db::block::get_b(&self.db, &hash)?)?;
+            file.write_all(&(bytes.len() as u32).to_be_bytes())?;
+            file.write_all(&bytes)?;
+            height += 1;
+        }
+        file.flush()?;
+        Ok(height)
+    }
+    pub fn import(&mut self, path: impl AsRef<Path>) -> Result<usize, Box<dyn Error>> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut len = [0; 4];
+        let mut imported = 0;
+        loop {
+            match file.read_exact(&mut len) {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err.into()),
+            }
+            let mut bytes = vec![0; u32::from_be_bytes(len) as usize];
+            file.read_exact(&mut bytes)?;
+            let block_b: BlockB = bincode::deserialize(&bytes)?;
+            let block_a = block_b.a()?;
+            if self.tree.get(&block_a.hash).is_some() {
+                continue;
+            }
+            self.validate_block_0(&block_a, util::timestamp())?;
+            self.validate_block_1(&block_a)?;
+            self.accept_block(&block_a, false);
+            imported += 1;
+        }
+        Ok(imported)
+    }
     pub fn sync_block(&mut self) -> BlockB {
-        let hashes_trusted = &self.states.trusted.hashes;
-        let hashes_dynamic = &self.states.dynamic.hashes;
-        if self.sync.index >= hashes_trusted.len() + hashes_dynamic.len() {
-            self.sync.index = 0;
+        if self.sync.index > self.height() || self.sync.index < self.pruned {
+            self.sync.index = self.pruned;
         }
-        let hash = if self.sync.index < hashes_trusted.len() {
-            hashes_trusted[self.sync.index]
-        } else {
-            hashes_dynamic[self.sync.index - hashes_trusted.len()]
-        };
+        let hash = self.hash_at_height(self.sync.index).unwrap();
         debug!("{} {} {}", "Sync".cyan(), self.sync.index.to_string().yellow(), hex::encode(hash));
         let block_b = db::block::get_b(&self.db, &hash).unwrap();
         self.sync.index += 1;
         block_b
     }
     pub fn forge_block(&mut self, timestamp: u32) -> Option<BlockA> {
-        if let Some(staker) = self.states.dynamic.next_staker(timestamp) {
-            if staker != self.key.address_bytes() || timestamp < self.states.dynamic.latest_block.timestamp + BLOCK_TIME_MIN as u32 {
+        let mut stakes = vec![];
+        if let Some(staker) = self.states.dynamic.next_staker(&self.spec, timestamp) {
+            if staker != self.key.address_bytes() || timestamp < self.states.dynamic.latest_block.timestamp + self.spec.block_time_min {
                 return None;
             }
         } else {
-            self.pending_stakes = vec![StakeA::sign(true, 0, 0, timestamp, &self.key).unwrap()];
+            stakes.push(StakeA::sign(self.spec.chain_id, true, 0, 0, timestamp, &self.key).unwrap());
         }
         let mut transactions = vec![];
-        let mut stakes = vec![];
-        for transaction_a in self.pending_transactions.clone() {
-            if transactions.len() >= BLOCK_TRANSACTIONS_LIMIT {
+        let mut pending_transactions = self.mempool.transactions().cloned().collect::<Vec<_>>();
+        let mut nonces: HashMap<AddressBytes, u64> = HashMap::new();
+        let mut spent: HashMap<AddressBytes, u128> = HashMap::new();
+        let mut locks: Vec<Hash> = vec![];
+        while let Some(index) = pending_transactions.iter().position(|x| {
+            x.nonce
+                == nonces
+                    .get(&x.input_address)
+                    .copied()
+                    .unwrap_or_else(|| self.states.dynamic.nonce(&x.input_address))
+                && spent.get(&x.input_address).copied().unwrap_or_default() + x.amount() + x.fee <= self.states.dynamic.balance(&x.input_address)
+                && x.htlc.as_ref().and_then(|y| y.lock()).is_none_or(|y| !locks.contains(y))
+                && Blockchain::validate_htlc(x, &self.states.dynamic, timestamp).is_ok()
+        }) {
+            if transactions.len() >= self.spec.block_transactions_limit {
                 break;
             }
+            let transaction_a = pending_transactions.remove(index);
+            nonces.insert(transaction_a.input_address, transaction_a.nonce + 1);
+            *spent.entry(transaction_a.input_address).or_default() += transaction_a.amount() + transaction_a.fee;
+            if let Some(lock) = transaction_a.htlc.as_ref().and_then(|x| x.lock()) {
+                locks.push(*lock);
+            }
             transactions.push(transaction_a);
         }
-        for stake_a in self.pending_stakes.clone() {
-            if stakes.len() >= BLOCK_STAKES_LIMIT {
+        for stake_a in self.mempool.stakes() {
+            if stakes.len() >= self.spec.block_stakes_limit {
+                break;
+            }
+            stakes.push(stake_a.clone());
+        }
+        let mut evidence = vec![];
+        let mut offenders = vec![];
+        for x in self.pending_evidence.iter() {
+            if evidence.len() >= self.spec.block_evidence_limit {
                 break;
             }
-            stakes.push(stake_a);
+            if let Ok(offender) = self.validate_evidence(x, &self.states.dynamic) {
+                if !offenders.contains(&offender) {
+                    offenders.push(offender);
+                    evidence.push(x.clone());
+                }
+            }
         }
         let block_a = if let Some(main) = self.tree.main() {
-            BlockA::sign(main.0, timestamp, transactions, stakes, &self.key, &self.states.dynamic.latest_block.beta)
+            BlockA::sign(
+                self.spec.chain_id,
+                main.0,
+                timestamp,
+                transactions,
+                stakes,
+                evidence,
+                &self.key,
+                &self.states.dynamic.latest_block.beta,
+            )
         } else {
-            BlockA::sign([0; 32], timestamp, transactions, stakes, &self.key, &GENESIS_BETA)
+            BlockA::sign(self.spec.chain_id, [0; 32], timestamp, transactions, stakes, evidence, &self.key, &GENESIS_BETA)
         }
         .unwrap();
         self.accept_block(&block_a, true);
         Some(block_a)
     }
     pub fn accept_block(&mut self, block_a: &BlockA, forged: bool) {
-        db::block::put(&block_a, &self.db).unwrap();
+        db::block::put(block_a, &self.db).unwrap();
         if self.tree.insert(block_a.hash, block_a.previous_hash, block_a.timestamp).unwrap() {
             warn!("{} {}", "Forked".red(), hex::encode(block_a.hash));
         }
         self.tree.sort_branches();
-        self.states
-            .update(&self.db, &self.tree.hashes_dynamic(self.trust_fork_after_blocks), self.trust_fork_after_blocks);
+        db::height::update(&self.tree, &self.db).unwrap();
+        self.states.update(
+            &self.db,
+            &self.spec,
+            &self.tree.hashes_dynamic(self.trust_fork_after_blocks),
+            self.trust_fork_after_blocks,
+        );
+        self.prune_blocks();
+        let hashes = &self.states.dynamic.hashes;
+        self.checkpoints.retain(|hash, _| hashes.contains(hash));
         let info_0 = if forged { "Forged".magenta() } else { "Accept".green() };
         let info_1 = hex::encode(block_a.hash);
         let info_2 = match block_a.transactions.len() {
@@ -126,10 +300,24 @@ impl Blockchain {
             0 => "0".red(),
             x => x.to_string().green(),
         };
-        if let Some(main) = self.tree.main() {
+        if let Some(&main) = self.tree.main() {
             if block_a.hash == main.0 {
-                self.pending_transactions.clear();
-                self.pending_stakes.clear();
+                for transaction_a in block_a.transactions.iter() {
+                    self.mempool.set_status(
+                        &self.spec,
+                        transaction_a.hash,
+                        Status::Included {
+                            block_hash: block_a.hash,
+                            height: main.1,
+                        },
+                    );
+                }
+                self.revalidate_mempool(util::timestamp());
+                let pending_evidence = std::mem::take(&mut self.pending_evidence);
+                self.pending_evidence = pending_evidence
+                    .into_iter()
+                    .filter(|x| self.validate_evidence(x, &self.states.dynamic).is_ok())
+                    .collect();
                 if !forged {
                     self.sync.new += 1.0;
                 }
@@ -139,56 +327,222 @@ impl Blockchain {
         }
         info!("{} {} {} {}", info_0, info_1, info_2, info_3);
     }
+    fn prune_blocks(&mut self) {
+        let keep = match self.prune {
+            Some(x) => x.max(1),
+            None => return,
+        };
+        let height = self.states.trusted.hashes.len();
+        if self.spec.snapshot_interval == 0 || !height.is_multiple_of(self.spec.snapshot_interval) {
+            return;
+        }
+        let pruned = height.saturating_sub(keep);
+        if pruned <= self.pruned {
+            return;
+        }
+        for hash in self.states.trusted.hashes[self.pruned..pruned].iter() {
+            db::block::prune(&self.db, hash).unwrap();
+        }
+        self.pruned = pruned;
+        db::pruned::put(self.pruned, &self.db).unwrap();
+        info!("Pruned blocks below {}", self.pruned.to_string().yellow());
+    }
+    pub fn is_pruned(&self, hash: &[u8]) -> bool {
+        let block_hashes = match db::height::get(&self.db, hash) {
+            Ok(_) => vec![hash.try_into().unwrap()],
+            Err(_) => db::inclusion::get(&self.db, hash).unwrap_or_default(),
+        };
+        block_hashes
+            .iter()
+            .any(|x| db::height::get(&self.db, x).is_ok_and(|height| height < self.pruned && self.hash_at_height(height) == Some(*x)))
+    }
+    pub fn revalidate_mempool(&mut self, timestamp: u32) {
+        let mut mempool = std::mem::take(&mut self.mempool);
+        mempool.expire(&self.spec, timestamp);
+        let dynamic = &self.states.dynamic;
+        let mut nonces: HashMap<AddressBytes, u64> = HashMap::new();
+        let mut spent: HashMap<AddressBytes, u128> = HashMap::new();
+        mempool.retain_transactions(&self.spec, |x| {
+            let nonce = nonces.entry(x.input_address).or_insert_with(|| dynamic.nonce(&x.input_address));
+            let spent = spent.entry(x.input_address).or_default();
+            if x.nonce != *nonce {
+                return Err("transaction nonce".into());
+            }
+            if *spent + x.amount() + x.fee > dynamic.balance(&x.input_address) {
+                return Err("transaction too expensive".into());
+            }
+            self.validate_transaction(x, dynamic.latest_block.timestamp, timestamp)?;
+            Blockchain::validate_htlc(x, dynamic, timestamp)?;
+            *nonce += 1;
+            *spent += x.amount() + x.fee;
+            Ok(())
+        });
+        let height = db::height::next(&self.db, &dynamic.latest_block.hash).ok();
+        mempool.retain_stakes(|x| height.is_some_and(|height| self.validate_stake(x, height, dynamic.latest_block.timestamp, timestamp).is_ok()));
+        self.mempool = mempool;
+        self.save_mempool();
+    }
+    fn save_mempool(&self) {
+        let (transactions, stakes) = self.mempool.entries();
+        db::pending::put(&transactions, &stakes, &self.db).unwrap();
+    }
+    pub fn next_nonce(&self, address: &AddressBytes) -> u64 {
+        let mut nonce = self.states.dynamic.nonce(address);
+        while self.mempool.transactions().any(|x| &x.input_address == address && x.nonce == nonce) {
+            nonce += 1;
+        }
+        nonce
+    }
     pub fn pending_blocks_push(&mut self, block_b: BlockB, timestamp: u32) -> Result<(), Box<dyn Error>> {
         if self.pending_blocks.len() < self.pending_blocks_limit {
             return Err("pending blocks limit reached".into());
         }
         let block_a = block_b.a()?;
         self.validate_block_0(&block_a, timestamp)?;
+        self.detect_equivocation(&block_a);
         self.pending_blocks.push(block_a);
         Ok(())
     }
-    pub fn pending_transactions_push(&mut self, transaction_b: TransactionB, timestamp: u32) -> Result<(), Box<dyn Error>> {
-        let transaction_a = transaction_b.a(None)?;
-        self.validate_transaction(&transaction_a, self.states.dynamic.latest_block.timestamp, timestamp)?;
-        if self.pending_transactions.iter().any(|x| x.hash == transaction_a.hash) {
-            return Err("transaction pending".into());
+    fn detect_equivocation(&mut self, block_a: &BlockA) {
+        for hash in self.tree.children(&block_a.previous_hash) {
+            let block_c = match db::block::get_c(&self.db, &hash) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            if let Err(err) = self.pending_evidence_push(Evidence::new(block_c, block_a.b().c())) {
+                debug!("{}", err);
+            }
+        }
+    }
+    pub fn pending_evidence_push(&mut self, evidence: Evidence) -> Result<(), Box<dyn Error>> {
+        let offender = self.validate_evidence(&evidence, &self.states.dynamic)?;
+        let hash = evidence.hash();
+        if db::evidence::get(&self.db, &hash).is_ok() {
+            return Err("evidence already included".into());
+        }
+        if self
+            .pending_evidence
+            .iter()
+            .any(|x| x.hash() == hash || (x.input_address().ok() == Some(offender) && x.block_c_0.previous_hash == evidence.block_c_0.previous_hash))
+        {
+            return Err("evidence pending".into());
+        }
+        warn!("{} {}", "Equivocation".red(), pea_address::address::encode(&offender).green());
+        self.pending_evidence.push(evidence);
+        while self.pending_evidence.len() > self.spec.pending_evidence_limit {
+            self.pending_evidence.remove(0);
+        }
+        Ok(())
+    }
+    pub fn checkpoint_hash(&self) -> Option<Hash> {
+        if self.spec.checkpoint_interval == 0 {
+            return None;
+        }
+        let height = self.height() / self.spec.checkpoint_interval * self.spec.checkpoint_interval;
+        height
+            .checked_sub(self.states.trusted.hashes.len())
+            .and_then(|index| self.states.dynamic.hashes.get(index))
+            .copied()
+    }
+    pub fn checkpoints_push(&mut self, checkpoint: Checkpoint) -> Result<(), Box<dyn Error>> {
+        if checkpoint.chain_id != self.spec.chain_id {
+            return Err("checkpoint chain id".into());
         }
-        if let Some(index) = self.pending_transactions.iter().position(|x| x.input_address == transaction_a.input_address) {
-            if transaction_a.fee <= self.pending_transactions[index].fee {
-                return Err("transaction fee too low".into());
+        if !self.states.dynamic.hashes.contains(&checkpoint.block_hash) {
+            return Err("checkpoint block not in dynamic chain".into());
+        }
+        let staker = checkpoint.input_address()?;
+        if !self.states.dynamic.stakers.contains(&staker) {
+            return Err("checkpoint not staker".into());
+        }
+        let votes = self.checkpoints.entry(checkpoint.block_hash).or_default();
+        if votes.contains_key(&staker) {
+            return Err("checkpoint vote pending".into());
+        }
+        votes.insert(staker, checkpoint);
+        self.update_finalized()
+    }
+    fn update_finalized(&mut self) -> Result<(), Box<dyn Error>> {
+        let dynamic = &self.states.dynamic;
+        let total: u128 = dynamic.stakers.iter().map(|x| dynamic.weight(x)).sum();
+        let start = match self.finalized.and_then(|x| dynamic.hashes.iter().position(|y| y == &x)) {
+            Some(index) => index + 1,
+            None => 0,
+        };
+        for hash in dynamic.hashes[start..].iter().rev() {
+            let weight: u128 = match self.checkpoints.get(hash) {
+                Some(votes) => votes.keys().filter(|x| dynamic.stakers.contains(x)).map(|x| dynamic.weight(x)).sum(),
+                None => continue,
+            };
+            if weight * 3 > total * 2 {
+                db::finalized::put(hash, &self.db)?;
+                info!("{} {}", "Finalized".magenta(), hex::encode(hash));
+                self.finalized = Some(*hash);
+                return Ok(());
             }
-            self.pending_transactions.remove(index);
         }
-        info!("Transaction {}", hex::encode(&transaction_a.hash).green());
-        self.pending_transactions.push(transaction_a);
-        self.pending_transactions.sort_by(|a, b| b.fee.cmp(&a.fee));
-        while self.pending_transactions.len() > PENDING_TRANSACTIONS_LIMIT {
-            self.pending_transactions.remove(self.pending_transactions.len() - 1);
+        Ok(())
+    }
+    pub fn pending_transactions_push(&mut self, transaction_b: TransactionB, timestamp: u32) -> Result<(), Box<dyn Error>> {
+        let transaction_a = transaction_b.a(None)?;
+        let hash = transaction_a.hash;
+        if let Err(err) = self
+            .validate_pending_transaction(&transaction_a, timestamp)
+            .and_then(|_| self.mempool.transaction_push(&self.spec, Entry::transaction(transaction_a, timestamp)))
+        {
+            if !matches!(self.transaction_status(&hash), Some(Status::Pending | Status::Included { .. })) {
+                self.mempool.set_status(&self.spec, hash, Status::Rejected { reason: err.to_string() });
+            }
+            return Err(err);
         }
+        self.save_mempool();
+        info!("Transaction {}", hex::encode(hash).green());
         Ok(())
     }
-    pub fn pending_stakes_push(&mut self, stake_b: StakeB, timestamp: u32) -> Result<(), Box<dyn Error>> {
-        let stake_a = stake_b.a(None)?;
-        self.validate_stake(&stake_a, self.states.dynamic.latest_block.timestamp, timestamp)?;
-        if self.pending_stakes.iter().any(|x| x.hash == stake_a.hash) {
-            return Err("stake pending".into());
+    fn validate_pending_transaction(&self, transaction_a: &TransactionA, timestamp: u32) -> Result<(), Box<dyn Error>> {
+        self.validate_transaction(transaction_a, self.states.dynamic.latest_block.timestamp, timestamp)?;
+        Blockchain::validate_htlc(transaction_a, &self.states.dynamic, timestamp)?;
+        if transaction_a.nonce < self.states.dynamic.nonce(&transaction_a.input_address) {
+            return Err("transaction nonce too low".into());
         }
-        if let Some(index) = self.pending_stakes.iter().position(|x| x.input_address == stake_a.input_address) {
-            if stake_a.fee <= self.pending_stakes[index].fee {
-                return Err("stake fee too low".into());
+        let mut spent = transaction_a.amount() + transaction_a.fee;
+        for x in self.mempool.transactions() {
+            if x.input_address == transaction_a.input_address && x.nonce != transaction_a.nonce {
+                spent += x.amount() + x.fee;
             }
-            self.pending_stakes.remove(index);
         }
-        info!("Stake {}", hex::encode(&stake_a.hash).green());
-        self.pending_stakes.push(stake_a);
-        self.pending_stakes.sort_by(|a, b| b.fee.cmp(&a.fee));
-        while self.pending_stakes.len() > PENDING_STAKES_LIMIT {
-            self.pending_stakes.remove(self.pending_stakes.len() - 1);
+        if spent > self.states.dynamic.balance(&transaction_a.input_address) {
+            return Err("transaction too expensive".into());
         }
+        if !self
+            .mempool
+            .transactions()
+            .any(|x| x.input_address == transaction_a.input_address && x.nonce == transaction_a.nonce)
+            && transaction_a.nonce != self.next_nonce(&transaction_a.input_address)
+        {
+            return Err("transaction nonce too high".into());
+        }
+        Ok(())
+    }
+    pub fn pending_stakes_push(&mut self, stake_b: StakeB, timestamp: u32) -> Result<(), Box<dyn Error>> {
+        let stake_a = stake_b.a(None)?;
+        let dynamic = &self.states.dynamic;
+        self.validate_stake(
+            &stake_a,
+            db::height::next(&self.db, &dynamic.latest_block.hash)?,
+            dynamic.latest_block.timestamp,
+            timestamp,
+        )?;
+        let hash = stake_a.hash;
+        self.mempool.stake_push(&self.spec, Entry::stake(stake_a, timestamp))?;
+        self.save_mempool();
+        info!("Stake {}", hex::encode(hash).green());
         Ok(())
     }
     pub fn validate_block_0(&self, block_a: &BlockA, timestamp: u32) -> Result<(), Box<dyn Error>> {
+        if block_a.chain_id != self.spec.chain_id {
+            return Err("block chain id".into());
+        }
         if self.tree.get(&block_a.hash).is_some() {
             return Err("block hash in tree".into());
         }
@@ -204,15 +558,47 @@ impl Blockchain {
             None => GENESIS_BETA,
         };
         Key::vrf_verify(&block_a.input_public_key, &block_a.pi, &previous_beta).ok_or("invalid proof")?;
+        let height = db::height::next(&self.db, &block_a.previous_hash)?;
         for stake_a in block_a.stakes.iter() {
-            self.validate_stake(stake_a, dynamic.latest_block.timestamp, timestamp)?;
+            self.validate_stake(stake_a, height, dynamic.latest_block.timestamp, timestamp)?;
         }
         for transaction_a in block_a.transactions.iter() {
             self.validate_transaction(transaction_a, dynamic.latest_block.timestamp, timestamp)?;
+            Blockchain::validate_htlc(transaction_a, &dynamic, block_a.timestamp)?;
         }
-        let input_addresses = block_a.transactions.iter().map(|x| x.input_address).collect::<Vec<AddressBytes>>();
-        if (1..input_addresses.len()).any(|i| input_addresses[i..].contains(&input_addresses[i - 1])) {
-            return Err("block includes multiple transactions from same input address".into());
+        let mut offenders = vec![];
+        for evidence in block_a.evidence.iter() {
+            offenders.push(self.validate_evidence(evidence, &dynamic)?);
+        }
+        if block_a.evidence.len() > self.spec.block_evidence_limit {
+            return Err("block evidence limit".into());
+        }
+        if (1..offenders.len()).any(|i| offenders[i..].contains(&offenders[i - 1])) {
+            return Err("block includes multiple evidence for same staker".into());
+        }
+        let mut nonces: HashMap<AddressBytes, u64> = HashMap::new();
+        let mut spent: HashMap<AddressBytes, u128> = HashMap::new();
+        for transaction_a in block_a.transactions.iter() {
+            let nonce = nonces
+                .entry(transaction_a.input_address)
+                .or_insert_with(|| dynamic.nonce(&transaction_a.input_address));
+            if transaction_a.nonce != *nonce {
+                return Err("transaction nonce".into());
+            }
+            *nonce += 1;
+            let spent = spent.entry(transaction_a.input_address).or_default();
+            *spent += transaction_a.amount() + transaction_a.fee;
+            if *spent > dynamic.balance(&transaction_a.input_address) {
+                return Err("block transactions too expensive".into());
+            }
+        }
+        let locks = block_a
+            .transactions
+            .iter()
+            .filter_map(|x| x.htlc.as_ref().and_then(|y| y.lock()))
+            .collect::<Vec<&Hash>>();
+        if (1..locks.len()).any(|i| locks[i..].contains(&locks[i - 1])) {
+            return Err("block includes multiple transactions releasing same lock".into());
         }
         let input_addresses = block_a.stakes.iter().map(|x| x.input_address).collect::<Vec<AddressBytes>>();
         if (1..input_addresses.len()).any(|i| input_addresses[i..].contains(&input_addresses[i - 1])) {
@@ -228,10 +614,10 @@ impl Blockchain {
                 return Err("block staker banned".into());
             }
         }
-        if block_a.timestamp < dynamic.latest_block.timestamp + BLOCK_TIME_MIN as u32 {
+        if block_a.timestamp < dynamic.latest_block.timestamp + self.spec.block_time_min {
             return Err("block timestamp early".into());
         }
-        if let Some(staker) = dynamic.next_staker(block_a.timestamp) {
+        if let Some(staker) = dynamic.next_staker(&self.spec, block_a.timestamp) {
             if staker != input_address {
                 return Err("block staker address".into());
             }
@@ -260,20 +646,57 @@ impl Blockchain {
         Ok(())
     }
     fn validate_transaction(&self, transaction_a: &TransactionA, previous_block_timestamp: u32, timestamp: u32) -> Result<(), Box<dyn Error>> {
-        if transaction_a.amount == 0 {
-            return Err("transaction amount zero".into());
+        if transaction_a.chain_id != self.spec.chain_id {
+            return Err("transaction chain id".into());
+        }
+        match &transaction_a.htlc {
+            Some(Htlc::Lock { .. }) if transaction_a.outputs.len() != 1 => return Err("transaction htlc lock outputs".into()),
+            Some(Htlc::Claim { .. }) | Some(Htlc::Refund { .. }) if !transaction_a.outputs.is_empty() => return Err("transaction htlc outputs".into()),
+            Some(Htlc::Claim { preimage, .. }) if preimage.len() > self.spec.htlc_preimage_limit => return Err("transaction htlc preimage limit".into()),
+            Some(Htlc::Claim { .. }) | Some(Htlc::Refund { .. }) => {}
+            _ if transaction_a.outputs.is_empty() => return Err("transaction outputs empty".into()),
+            _ => {}
+        }
+        if transaction_a.outputs.len() > self.spec.transaction_outputs_limit {
+            return Err("transaction outputs limit".into());
+        }
+        for (output_address, amount) in transaction_a.outputs.iter() {
+            if *amount == 0 {
+                return Err("transaction amount zero".into());
+            }
+            if *amount != pea_int::floor(*amount) {
+                return Err("transaction amount floor".into());
+            }
+            if transaction_a.input_address == *output_address {
+                return Err("transaction input output".into());
+            }
         }
         if transaction_a.fee == 0 {
             return Err("transaction fee zero".into());
         }
-        if transaction_a.amount != pea_int::floor(transaction_a.amount) {
-            return Err("transaction amount floor".into());
-        }
         if transaction_a.fee != pea_int::floor(transaction_a.fee) {
             return Err("transaction fee floor".into());
         }
-        if transaction_a.input_address == transaction_a.output_address {
-            return Err("transaction input output".into());
+        if transaction_a.memo.len() > self.spec.transaction_memo_limit {
+            return Err("transaction memo limit".into());
+        }
+        if transaction_a.fee < transaction_a.memo.len() as u128 * self.spec.transaction_memo_fee_per_byte {
+            return Err("transaction memo fee too low".into());
+        }
+        if let Some(multisig) = &transaction_a.multisig {
+            if multisig.addresses.len() > self.spec.multisig_addresses_limit {
+                return Err("transaction multisig addresses limit".into());
+            }
+            if multisig.threshold == 0 || multisig.threshold as usize > multisig.addresses.len() {
+                return Err("transaction multisig threshold".into());
+            }
+            if multisig.addresses.windows(2).any(|x| x[0] >= x[1]) {
+                return Err("transaction multisig addresses order".into());
+            }
+            if multisig.address() != transaction_a.input_address {
+                return Err("transaction multisig address".into());
+            }
+            multisig.verify(&transaction_a.hash)?;
         }
         let balance = self.states.dynamic.balance(&transaction_a.input_address);
         if transaction_a.timestamp > timestamp + self.time_delta {
@@ -282,15 +705,79 @@ impl Blockchain {
         if transaction_a.timestamp < previous_block_timestamp {
             return Err("transaction timestamp ancient".into());
         }
-        if transaction_a.amount + transaction_a.fee > balance {
+        if transaction_a.amount() + transaction_a.fee > balance {
             return Err("transaction too expensive".into());
         }
-        if db::transaction::get_b(&self.db, &transaction_a.hash).is_ok() {
-            return Err("transaction in chain".into());
+        Ok(())
+    }
+    fn validate_evidence(&self, evidence: &Evidence, dynamic: &Dynamic) -> Result<AddressBytes, Box<dyn Error>> {
+        let offender = evidence.input_address()?;
+        let (block_c_0, block_c_1) = (&evidence.block_c_0, &evidence.block_c_1);
+        if block_c_0.chain_id != self.spec.chain_id {
+            return Err("evidence chain id".into());
+        }
+        if dynamic.slashed(&offender, &block_c_0.previous_hash) {
+            return Err("evidence already slashed".into());
+        }
+        if db::height::next(&self.db, &block_c_0.previous_hash)? + self.spec.unbonding_blocks < dynamic.issuance().height {
+            return Err("evidence too old".into());
+        }
+        let previous_timestamp = match db::block::get_c(&self.db, &block_c_0.previous_hash) {
+            Ok(block_c) => block_c.timestamp,
+            Err(_) if block_c_0.previous_hash == [0; 32] => self.spec.genesis.timestamp,
+            Err(err) => return Err(err),
+        };
+        if state::offline(&self.spec, block_c_0.timestamp, previous_timestamp) != state::offline(&self.spec, block_c_1.timestamp, previous_timestamp) {
+            return Err("evidence slot".into());
+        }
+        if dynamic.staked(&offender) == 0 && dynamic.unbonding(&offender).is_empty() {
+            return Err("evidence offender not staked".into());
+        }
+        Ok(offender)
+    }
+    fn validate_htlc(transaction_a: &TransactionA, dynamic: &Dynamic, timestamp: u32) -> Result<(), Box<dyn Error>> {
+        match &transaction_a.htlc {
+            Some(Htlc::Lock { timeout, .. }) if *timeout <= timestamp => return Err("transaction htlc timeout".into()),
+            Some(Htlc::Claim { lock, preimage }) => {
+                let lock = dynamic.lock(lock).ok_or("transaction htlc lock not found")?;
+                if lock.recipient != transaction_a.input_address {
+                    return Err("transaction htlc claim recipient".into());
+                }
+                if Htlc::hash_lock(preimage) != lock.hash_lock {
+                    return Err("transaction htlc preimage".into());
+                }
+                if timestamp >= lock.timeout {
+                    return Err("transaction htlc claim expired".into());
+                }
+            }
+            Some(Htlc::Refund { lock }) => {
+                let lock = dynamic.lock(lock).ok_or("transaction htlc lock not found")?;
+                if lock.sender != transaction_a.input_address {
+                    return Err("transaction htlc refund sender".into());
+                }
+                if timestamp < lock.timeout {
+                    return Err("transaction htlc refund early".into());
+                }
+            }
+            _ => {}
         }
         Ok(())
     }
-    fn validate_stake(&self, stake_a: &StakeA, previous_block_timestamp: u32, timestamp: u32) -> Result<(), Box<dyn Error>> {
+    fn validate_stake(&self, stake_a: &StakeA, height: usize, previous_block_timestamp: u32, timestamp: u32) -> Result<(), Box<dyn Error>> {
+        match stake_a.version {
+            0 if height >= self.spec.stake_v1_height => return Err("stake version deprecated".into()),
+            0..=2 => {}
+            _ => return Err("stake version".into()),
+        }
+        if stake_a.version < 2 && (stake_a.delegate.is_some() || stake_a.commission != 0) {
+            return Err("stake version".into());
+        }
+        if stake_a.commission > 100 {
+            return Err("stake commission".into());
+        }
+        if stake_a.chain_id != self.spec.chain_id {
+            return Err("stake chain id".into());
+        }
         if stake_a.amount == 0 {
             return Err("stake amount zero".into());
         }
@@ -317,9 +804,484 @@ impl Blockchain {
         } else if stake_a.fee > balance {
             return Err("stake withdraw fee too expensive".into());
         }
-        if db::stake::get_b(&self.db, &stake_a.hash).is_ok() {
+        if let Some(staker) = stake_a.delegate {
+            if stake_a.commission != 0 {
+                return Err("stake delegate commission".into());
+            }
+            if staker == stake_a.input_address {
+                return Err("stake delegate self".into());
+            }
+            if stake_a.deposit && self.states.dynamic.staked(&staker) == 0 {
+                return Err("stake delegate not staker".into());
+            }
+            if !stake_a.deposit && stake_a.amount > self.states.dynamic.delegation(&stake_a.input_address, &staker) {
+                return Err("stake undelegate too expensive".into());
+            }
+        }
+        if db::stake::get_b(&self.db, &stake_a.hash).is_ok() || !db::inclusion::get(&self.db, &stake_a.hash)?.is_empty() {
             return Err("stake in chain".into());
         }
         Ok(())
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+    use db::history::Direction;
+    use pea_address::address;
+    use rocksdb::IteratorMode;
+    use std::collections::BTreeMap;
+    use tempdir::TempDir;
+    fn blockchain(f: impl FnOnce(&mut ChainSpec)) -> (TempDir, Blockchain) {
+        let key = Key::generate();
+        let mut spec = ChainSpec::devnet();
+        spec.genesis.balances = BTreeMap::from([(address::encode(&key.address_bytes()), 1_000 * COIN)]);
+        spec.genesis.staked = BTreeMap::from([(address::encode(&key.address_bytes()), 1_000 * COIN)]);
+        f(&mut spec);
+        let tempdir = TempDir::new("peacash-db").unwrap();
+        let blockchain = Blockchain::new(db::open(tempdir.path().to_str().unwrap()), key, 2, 256, 1, None, spec);
+        (tempdir, blockchain)
+    }
+    fn forge(blockchain: &mut Blockchain, n: usize) {
+        for _ in 0..n {
+            assert!(blockchain
+                .forge_block(blockchain.states.dynamic.latest_block.timestamp + blockchain.spec.block_time_min)
+                .is_some());
+        }
+    }
+    fn transfer(blockchain: &Blockchain, key: &Key, nonce: u64, output_address: AddressBytes) -> TransactionA {
+        TransactionA::sign(
+            blockchain.spec.chain_id,
+            nonce,
+            output_address,
+            COIN,
+            COIN,
+            blockchain.states.dynamic.latest_block.timestamp,
+            key,
+        )
+        .unwrap()
+    }
+    fn tip(blockchain: &Blockchain) -> Hash {
+        blockchain.tree.main().map(|x| x.0).unwrap_or([0; 32])
+    }
+    fn sign(blockchain: &Blockchain, keys: &[Key], previous_hash: Hash, timestamp: u32, transactions: Vec<TransactionA>, evidence: Vec<Evidence>) -> BlockA {
+        let dynamic = blockchain.states.dynamic_fork(blockchain, &previous_hash).unwrap();
+        let staker = dynamic.next_staker(&blockchain.spec, timestamp).unwrap();
+        let key = keys.iter().find(|x| x.address_bytes() == staker).unwrap();
+        let beta = if previous_hash == [0; 32] { GENESIS_BETA } else { dynamic.latest_block.beta };
+        BlockA::sign(blockchain.spec.chain_id, previous_hash, timestamp, transactions, vec![], evidence, key, &beta).unwrap()
+    }
+    #[test]
+    fn test_evidence_replay() {
+        let keys = [Key::generate(), Key::generate()];
+        let (_tempdir, mut blockchain) =
+            blockchain(|spec| spec.genesis.staked = keys.iter().map(|x| (address::encode(&x.address_bytes()), 1_000 * COIN)).collect());
+        let timestamp = blockchain.spec.genesis.timestamp;
+        let block_a_0 = sign(&blockchain, &keys, [0; 32], timestamp + 1, vec![], vec![]);
+        let block_a_1 = sign(&blockchain, &keys, [0; 32], timestamp + 2, vec![], vec![]);
+        let offender = block_a_0.input_address();
+        let evidence = Evidence::new(block_a_0.b().c(), block_a_1.b().c());
+        blockchain.accept_block(&block_a_0, false);
+        blockchain.pending_evidence_push(evidence.clone()).unwrap();
+        let block_a = sign(&blockchain, &keys, tip(&blockchain), timestamp + 3, vec![], vec![evidence.clone()]);
+        blockchain.validate_block_0(&block_a, block_a.timestamp).unwrap();
+        blockchain.accept_block(&block_a, false);
+        assert_eq!(blockchain.states.dynamic.staked(&offender), 0);
+        assert!(blockchain.pending_evidence.is_empty());
+        assert!(blockchain.pending_evidence_push(evidence.clone()).is_err());
+        let block_a = sign(&blockchain, &keys, tip(&blockchain), timestamp + 4, vec![], vec![evidence]);
+        assert_eq!(
+            blockchain.validate_block_0(&block_a, block_a.timestamp).unwrap_err().to_string(),
+            "evidence already slashed"
+        );
+    }
+    fn assert_state_eq<T: State>(a: &T, b: &T) {
+        assert_eq!(a.get_latest_block().hash, b.get_latest_block().hash);
+        assert_eq!(a.get_stakers(), b.get_stakers());
+        assert_eq!(a.get_map_balance(), b.get_map_balance());
+        assert_eq!(a.get_map_staked(), b.get_map_staked());
+        assert_eq!(a.get_map_nonce(), b.get_map_nonce());
+        assert_eq!(a.get_map_locked(), b.get_map_locked());
+        assert_eq!(a.get_unbonding(), b.get_unbonding());
+        assert_eq!(a.get_map_delegation(), b.get_map_delegation());
+        assert_eq!(a.get_map_delegated(), b.get_map_delegated());
+        assert_eq!(a.get_map_commission(), b.get_map_commission());
+        assert_eq!(a.get_commission_changes(), b.get_commission_changes());
+        assert_eq!(a.get_map_slashed(), b.get_map_slashed());
+        assert_eq!(a.get_issuance(), b.get_issuance());
+    }
+    #[test]
+    fn test_restore_snapshot() {
+        let (_tempdir, mut blockchain) = blockchain(|spec| spec.snapshot_interval = 2);
+        let mut spec = blockchain.spec.clone();
+        let mut timestamp = blockchain.spec.genesis.timestamp;
+        for _ in 0..9 {
+            timestamp += blockchain.spec.block_time_max() + blockchain.spec.block_time_min;
+            assert!(blockchain.forge_block(timestamp).is_some());
+        }
+        assert!(blockchain.states.trusted.get_issuance().burned > 0);
+        let states = (blockchain.states.trusted.clone(), blockchain.states.dynamic.clone());
+        let mut blockchain = Blockchain::new(blockchain.db, Key::generate(), 2, 256, 1, None, spec.clone());
+        blockchain.load().unwrap();
+        assert_eq!(
+            Trusted::restore(&blockchain.db, &blockchain.states.trusted.hashes)
+                .unwrap()
+                .unwrap()
+                .hashes
+                .len(),
+            6
+        );
+        assert_state_eq(&blockchain.states.trusted, &states.0);
+        assert_state_eq(&blockchain.states.dynamic, &states.1);
+        for key in blockchain
+            .db
+            .iterator_cf(db::snapshots(&blockchain.db), IteratorMode::Start)
+            .map(|x| x.unwrap().0)
+            .collect::<Vec<_>>()
+        {
+            blockchain.db.delete_cf(db::snapshots(&blockchain.db), key).unwrap();
+        }
+        spec.snapshot_interval = 0;
+        let mut blockchain = Blockchain::new(blockchain.db, Key::generate(), 2, 256, 1, None, spec);
+        blockchain.load().unwrap();
+        assert!(db::snapshot::get(&blockchain.db).unwrap().is_empty());
+        assert_state_eq(&blockchain.states.trusted, &states.0);
+        assert_state_eq(&blockchain.states.dynamic, &states.1);
+    }
+    #[test]
+    fn test_load_pruned_without_snapshot() {
+        let (_tempdir, mut blockchain) = blockchain(|_| {});
+        forge(&mut blockchain, 5);
+        db::pruned::put(2, &blockchain.db).unwrap();
+        let spec = blockchain.spec.clone();
+        let mut blockchain = Blockchain::new(blockchain.db, Key::generate(), 2, 256, 1, None, spec);
+        assert_eq!(blockchain.load().unwrap_err().to_string(), "pruned database without snapshot");
+    }
+    fn history(blockchain: &Blockchain, address: &AddressBytes) -> Vec<(Hash, Hash, Direction)> {
+        blockchain
+            .history(address, 0, usize::MAX)
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.hash, x.block_hash, x.direction))
+            .collect()
+    }
+    #[test]
+    fn test_history_fork() {
+        let (_tempdir, mut blockchain) = blockchain(|_| {});
+        blockchain.trust_fork_after_blocks = 10;
+        let keys = [Key::from_slice(&blockchain.key.secret_key_bytes())];
+        let timestamp = blockchain.spec.genesis.timestamp;
+        let block_a_0 = sign(&blockchain, &keys, [0; 32], timestamp + 1, vec![], vec![]);
+        blockchain.accept_block(&block_a_0, false);
+        let transaction_a = transfer(&blockchain, &keys[0], 0, [1; 20]);
+        let block_a_1 = sign(&blockchain, &keys, block_a_0.hash, timestamp + 2, vec![transaction_a.clone()], vec![]);
+        blockchain.accept_block(&block_a_1, false);
+        assert_eq!(history(&blockchain, &[1; 20]), vec![(transaction_a.hash, block_a_1.hash, Direction::In)]);
+        let block_b_1 = sign(&blockchain, &keys, block_a_0.hash, timestamp + 3, vec![], vec![]);
+        blockchain.accept_block(&block_b_1, false);
+        assert_eq!(history(&blockchain, &[1; 20]).len(), 1);
+        let block_b_2 = sign(&blockchain, &keys, block_b_1.hash, timestamp + 4, vec![], vec![]);
+        blockchain.accept_block(&block_b_2, false);
+        assert_eq!(tip(&blockchain), block_b_2.hash);
+        assert!(history(&blockchain, &[1; 20]).is_empty());
+        assert!(history(&blockchain, &keys[0].address_bytes()).is_empty());
+        assert_eq!(db::consistency::verify(&blockchain.db).unwrap(), Vec::<String>::new());
+        let block_a_2 = sign(&blockchain, &keys, block_a_1.hash, timestamp + 5, vec![], vec![]);
+        blockchain.accept_block(&block_a_2, false);
+        let block_a_3 = sign(&blockchain, &keys, block_a_2.hash, timestamp + 6, vec![], vec![]);
+        blockchain.accept_block(&block_a_3, false);
+        assert_eq!(tip(&blockchain), block_a_3.hash);
+        assert_eq!(history(&blockchain, &[1; 20]), vec![(transaction_a.hash, block_a_1.hash, Direction::In)]);
+        assert_eq!(
+            history(&blockchain, &keys[0].address_bytes()),
+            vec![(transaction_a.hash, block_a_1.hash, Direction::Out)]
+        );
+        assert_eq!(db::consistency::verify(&blockchain.db).unwrap(), Vec::<String>::new());
+    }
+    #[test]
+    fn test_stake_version() {
+        let (_tempdir, mut blockchain) = blockchain(|spec| spec.stake_v1_height = 1);
+        let stake = |blockchain: &Blockchain, version: u8| {
+            let timestamp = blockchain.states.dynamic.latest_block.timestamp;
+            let mut stake_a = StakeA::sign(blockchain.spec.chain_id, true, COIN, COIN, timestamp, &blockchain.key).unwrap();
+            stake_a.version = version;
+            stake_a.hash = stake_a.hash();
+            stake_a.signature = blockchain.key.sign(&stake_a.hash).unwrap();
+            stake_a
+        };
+        let stake_a = stake(&blockchain, 0);
+        blockchain.pending_stakes_push(stake_a.b(), stake_a.timestamp).unwrap();
+        forge(&mut blockchain, 1);
+        assert_eq!(blockchain.states.dynamic.latest_block.stakes[0].hash, stake_a.hash);
+        let stake_a = stake(&blockchain, 0);
+        assert_eq!(
+            blockchain.pending_stakes_push(stake_a.b(), stake_a.timestamp).unwrap_err().to_string(),
+            "stake version deprecated"
+        );
+        let stake_a = stake(&blockchain, 1);
+        blockchain.pending_stakes_push(stake_a.b(), stake_a.timestamp).unwrap();
+        let stake_a = stake(&blockchain, 3);
+        assert_eq!(
+            blockchain.pending_stakes_push(stake_a.b(), stake_a.timestamp).unwrap_err().to_string(),
+            "stake version"
+        );
+    }
+    #[test]
+    fn test_tree_restore() {
+        let (_tempdir, mut blockchain) = blockchain(|_| {});
+        let keys = [Key::from_slice(&blockchain.key.secret_key_bytes())];
+        let timestamp = blockchain.spec.genesis.timestamp;
+        let block_a_0 = sign(&blockchain, &keys, [0; 32], timestamp + 1, vec![], vec![]);
+        blockchain.accept_block(&block_a_0, false);
+        let block_a_1 = sign(&blockchain, &keys, block_a_0.hash, timestamp + 2, vec![], vec![]);
+        blockchain.accept_block(&block_a_1, false);
+        let block_a_2 = sign(&blockchain, &keys, block_a_1.hash, timestamp + 3, vec![], vec![]);
+        blockchain.accept_block(&block_a_2, false);
+        let block_b_2 = sign(&blockchain, &keys, block_a_1.hash, timestamp + 4, vec![], vec![]);
+        blockchain.accept_block(&block_b_2, false);
+        assert_eq!(tip(&blockchain), block_a_2.hash);
+        let mut tree = Tree::default();
+        db::tree::restore(&mut tree, &blockchain.db, blockchain.trust_fork_after_blocks).unwrap();
+        assert_eq!(tree.main(), blockchain.tree.main());
+        assert_eq!(tree.size(), blockchain.tree.size());
+        assert_eq!(tree.children(&block_a_1.hash), vec![block_a_2.hash, block_b_2.hash]);
+        assert_eq!(tree.hashes(2), blockchain.tree.hashes(2));
+    }
+    #[test]
+    fn test_history_htlc() {
+        let recipient = Key::generate();
+        let (_tempdir, mut blockchain) = blockchain(|spec| {
+            spec.genesis.balances.insert(address::encode(&recipient.address_bytes()), 1_000 * COIN);
+        });
+        let key = Key::from_slice(&blockchain.key.secret_key_bytes());
+        forge(&mut blockchain, 1);
+        let chain_id = blockchain.spec.chain_id;
+        let timestamp = blockchain.states.dynamic.latest_block.timestamp;
+        let preimage = vec![1; 32];
+        let outputs = vec![(recipient.address_bytes(), COIN)];
+        let htlc = Htlc::Lock {
+            hash_lock: Htlc::hash_lock(&preimage),
+            timeout: timestamp + 100,
+        };
+        let lock_0 = TransactionA::sign_htlc(chain_id, 0, outputs.clone(), COIN, htlc, timestamp, &key).unwrap();
+        let htlc = Htlc::Lock {
+            hash_lock: [0; 32],
+            timeout: timestamp + 3,
+        };
+        let lock_1 = TransactionA::sign_htlc(chain_id, 1, outputs, COIN, htlc, timestamp, &key).unwrap();
+        blockchain.pending_transactions_push(lock_0.b(), timestamp).unwrap();
+        blockchain.pending_transactions_push(lock_1.b(), timestamp).unwrap();
+        forge(&mut blockchain, 1);
+        let timestamp = blockchain.states.dynamic.latest_block.timestamp;
+        let htlc = Htlc::Claim { lock: lock_0.hash, preimage };
+        let claim = TransactionA::sign_htlc(chain_id, 0, vec![], COIN, htlc, timestamp, &recipient).unwrap();
+        blockchain.pending_transactions_push(claim.b(), timestamp).unwrap();
+        forge(&mut blockchain, 3);
+        let timestamp = blockchain.states.dynamic.latest_block.timestamp;
+        let refund = TransactionA::sign_htlc(chain_id, 2, vec![], COIN, Htlc::Refund { lock: lock_1.hash }, timestamp, &key).unwrap();
+        blockchain.pending_transactions_push(refund.b(), timestamp).unwrap();
+        forge(&mut blockchain, 1);
+        let directions = |address: &AddressBytes| {
+            let mut vec = history(&blockchain, address)
+                .into_iter()
+                .map(|(hash, _, direction)| (hash, direction))
+                .collect::<Vec<_>>();
+            vec.sort_by_key(|x| x.0);
+            vec
+        };
+        assert_eq!(directions(&recipient.address_bytes()), vec![(claim.hash, Direction::In)]);
+        let mut vec = vec![(lock_0.hash, Direction::Out), (lock_1.hash, Direction::Out), (refund.hash, Direction::In)];
+        vec.sort_by_key(|x| x.0);
+        assert_eq!(directions(&key.address_bytes()), vec);
+    }
+    #[test]
+    fn test_nonce_pending() {
+        let (_tempdir, mut blockchain) = blockchain(|_| {});
+        let timestamp = blockchain.states.dynamic.latest_block.timestamp;
+        let transactions = (0..3).map(|x| transfer(&blockchain, &blockchain.key, x, [1; 20])).collect::<Vec<_>>();
+        assert_eq!(
+            blockchain.pending_transactions_push(transactions[1].b(), timestamp).unwrap_err().to_string(),
+            "transaction nonce too high"
+        );
+        for transaction_a in transactions.iter() {
+            blockchain.pending_transactions_push(transaction_a.b(), timestamp).unwrap();
+        }
+        assert_eq!(blockchain.mempool.transactions().count(), 3);
+        assert_eq!(blockchain.next_nonce(&blockchain.key.address_bytes()), 3);
+        forge(&mut blockchain, 1);
+        assert_eq!(
+            blockchain.states.dynamic.latest_block.transactions.iter().map(|x| x.nonce).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(blockchain.states.dynamic.nonce(&blockchain.key.address_bytes()), 3);
+        assert_eq!(blockchain.mempool.transactions().count(), 0);
+        assert!(blockchain.pending_transactions_push(transactions[0].b(), timestamp).is_err());
+        let transaction_a = transfer(&blockchain, &blockchain.key, 0, [1; 20]);
+        let timestamp = blockchain.states.dynamic.latest_block.timestamp;
+        assert_eq!(
+            blockchain.pending_transactions_push(transaction_a.b(), timestamp).unwrap_err().to_string(),
+            "transaction nonce too low"
+        );
+    }
+    #[test]
+    fn test_nonce_block() {
+        let (_tempdir, mut blockchain) = blockchain(|_| {});
+        let keys = [Key::from_slice(&blockchain.key.secret_key_bytes())];
+        let block_a = sign(&blockchain, &keys, [0; 32], blockchain.spec.genesis.timestamp + 1, vec![], vec![]);
+        blockchain.accept_block(&block_a, false);
+        let transactions = (0..3).map(|x| transfer(&blockchain, &keys[0], x, [1; 20])).collect::<Vec<_>>();
+        let validate = |blockchain: &Blockchain, transactions: Vec<TransactionA>| {
+            let block_a = sign(
+                blockchain,
+                &keys,
+                tip(blockchain),
+                blockchain.states.dynamic.latest_block.timestamp + 1,
+                transactions,
+                vec![],
+            );
+            blockchain.validate_block_0(&block_a, block_a.timestamp).map(|_| block_a)
+        };
+        let err = validate(&blockchain, vec![transactions[1].clone(), transactions[0].clone()]).unwrap_err();
+        assert_eq!(err.to_string(), "transaction nonce");
+        let err = validate(&blockchain, vec![transactions[0].clone(), transactions[2].clone()]).unwrap_err();
+        assert_eq!(err.to_string(), "transaction nonce");
+        let block_a = validate(&blockchain, vec![transactions[0].clone(), transactions[1].clone()]).unwrap();
+        blockchain.accept_block(&block_a, false);
+        assert_eq!(blockchain.states.dynamic.nonce(&keys[0].address_bytes()), 2);
+        let transaction_a = transfer(&blockchain, &keys[0], 0, [1; 20]);
+        assert_eq!(validate(&blockchain, vec![transaction_a]).unwrap_err().to_string(), "transaction nonce");
+    }
+    #[test]
+    fn test_mempool_reload() {
+        let (_tempdir, mut blockchain) = blockchain(|_| {});
+        let transaction_a = transfer(&blockchain, &blockchain.key, 0, [1; 20]);
+        blockchain.pending_transactions_push(transaction_a.b(), util::timestamp()).unwrap();
+        let stake_a = StakeA::sign(blockchain.spec.chain_id, true, COIN, COIN, util::timestamp(), &blockchain.key).unwrap();
+        blockchain.pending_stakes_push(stake_a.b(), util::timestamp()).unwrap();
+        let spec = blockchain.spec.clone();
+        let mut blockchain = Blockchain::new(blockchain.db, Key::generate(), 2, 256, 1, None, spec);
+        blockchain.load().unwrap();
+        assert_eq!(blockchain.mempool.transactions().map(|x| x.hash).collect::<Vec<_>>(), vec![transaction_a.hash]);
+        assert_eq!(blockchain.transaction_status(&transaction_a.hash), Some(Status::Pending));
+        assert_eq!(blockchain.mempool.stakes().map(|x| x.hash).collect::<Vec<_>>(), vec![stake_a.hash]);
+    }
+    #[test]
+    fn test_mempool_expiry() {
+        let (_tempdir, mut blockchain) = blockchain(|_| {});
+        let timestamp = blockchain.states.dynamic.latest_block.timestamp;
+        let transaction_a = transfer(&blockchain, &blockchain.key, 0, [1; 20]);
+        blockchain.pending_transactions_push(transaction_a.b(), timestamp).unwrap();
+        blockchain.revalidate_mempool(timestamp + blockchain.spec.pending_expiry);
+        assert_eq!(blockchain.mempool.transactions().count(), 1);
+        blockchain.revalidate_mempool(timestamp + blockchain.spec.pending_expiry + 1);
+        assert_eq!(blockchain.mempool.transactions().count(), 0);
+        assert_eq!(
+            blockchain.mempool.status(&transaction_a.hash),
+            Some(&Status::Evicted { reason: "expired".to_string() })
+        );
+        assert!(db::pending::get(&blockchain.db).unwrap().0.is_empty());
+    }
+    #[test]
+    fn test_confirmations() {
+        let (_tempdir, mut blockchain) = blockchain(|_| {});
+        let transaction_a = transfer(&blockchain, &blockchain.key, 0, [1; 20]);
+        blockchain
+            .pending_transactions_push(transaction_a.b(), blockchain.states.dynamic.latest_block.timestamp)
+            .unwrap();
+        assert_eq!(blockchain.inclusion(&transaction_a.hash), None);
+        forge(&mut blockchain, 1);
+        let (block_hash, height) = (tip(&blockchain), blockchain.height());
+        assert_eq!(blockchain.inclusion(&transaction_a.hash), Some((block_hash, height, 1)));
+        forge(&mut blockchain, 2);
+        assert_eq!(blockchain.inclusion(&transaction_a.hash), Some((block_hash, height, 3)));
+    }
+    #[test]
+    fn test_export_import() {
+        let (_tempdir, mut blockchain_0) = blockchain(|_| {});
+        forge(&mut blockchain_0, 5);
+        let tempdir = TempDir::new("peacash-db").unwrap();
+        let path = tempdir.path().join("export");
+        assert_eq!(blockchain_0.export(&path).unwrap(), 5);
+        let spec = blockchain_0.spec.clone();
+        let mut blockchain_1 = Blockchain::new(db::open(tempdir.path().join("db").to_str().unwrap()), Key::generate(), 2, 256, 1, None, spec);
+        assert_eq!(blockchain_1.import(&path).unwrap(), 5);
+        assert_eq!(blockchain_1.import(&path).unwrap(), 0);
+        assert_eq!(blockchain_1.height(), blockchain_0.height());
+        assert_eq!(blockchain_1.states.dynamic.latest_block.hash, blockchain_0.states.dynamic.latest_block.hash);
+    }
+    #[test]
+    fn test_transaction_status() {
+        let (_tempdir, mut blockchain) = blockchain(|spec| spec.pending_status_limit = 2);
+        let timestamp = blockchain.states.dynamic.latest_block.timestamp;
+        let transaction_a_0 = transfer(&blockchain, &blockchain.key, 0, [1; 20]);
+        let transaction_a_1 = transfer(&blockchain, &Key::generate(), 0, [2; 20]);
+        blockchain.pending_transactions_push(transaction_a_0.b(), timestamp).unwrap();
+        let err = blockchain.pending_transactions_push(transaction_a_1.b(), timestamp).unwrap_err();
+        assert!(blockchain.pending_transactions_push(transaction_a_0.b(), timestamp).is_err());
+        assert_eq!(blockchain.transaction_status(&transaction_a_0.hash), Some(Status::Pending));
+        assert_eq!(
+            blockchain.transaction_status(&transaction_a_1.hash),
+            Some(Status::Rejected { reason: err.to_string() })
+        );
+        forge(&mut blockchain, 1);
+        let included = Some(Status::Included {
+            block_hash: tip(&blockchain),
+            height: blockchain.height(),
+        });
+        assert_eq!(blockchain.transaction_status(&transaction_a_0.hash), included);
+        let timestamp = blockchain.states.dynamic.latest_block.timestamp;
+        assert!(blockchain.pending_transactions_push(transaction_a_0.b(), timestamp).is_err());
+        assert_eq!(blockchain.transaction_status(&transaction_a_0.hash), included);
+        let transaction_a_2 = transfer(&blockchain, &blockchain.key, 1, [1; 20]);
+        blockchain.pending_transactions_push(transaction_a_2.b(), timestamp).unwrap();
+        let transaction_a_3 = transfer(&blockchain, &blockchain.key, 2, [1; 20]);
+        blockchain.pending_transactions_push(transaction_a_3.b(), timestamp).unwrap();
+        assert_eq!(blockchain.mempool.status(&transaction_a_0.hash), None);
+        assert_eq!(blockchain.transaction_status(&transaction_a_0.hash), included);
+        assert_eq!(blockchain.transaction_status(&transaction_a_2.hash), Some(Status::Pending));
+    }
+    #[test]
+    fn test_finalized_restore() {
+        let (_tempdir, mut blockchain) = blockchain(|spec| spec.checkpoint_interval = 2);
+        forge(&mut blockchain, 5);
+        let hash = blockchain.checkpoint_hash().unwrap();
+        let checkpoint = Checkpoint::sign(blockchain.spec.chain_id, hash, &blockchain.key).unwrap();
+        blockchain.checkpoints_push(checkpoint).unwrap();
+        assert_eq!(blockchain.finalized, Some(hash));
+        let spec = blockchain.spec.clone();
+        let mut blockchain = Blockchain::new(blockchain.db, Key::generate(), 2, 256, 1, None, spec);
+        blockchain.load().unwrap();
+        assert_eq!(blockchain.finalized, Some(hash));
+    }
+    #[test]
+    fn test_verify_db() {
+        let (_tempdir, mut blockchain) = blockchain(|_| {});
+        forge(&mut blockchain, 3);
+        let transaction_b = transfer(&blockchain, &blockchain.key, 0, [1; 20]).b();
+        blockchain
+            .pending_transactions_push(transaction_b, blockchain.states.dynamic.latest_block.timestamp)
+            .unwrap();
+        forge(&mut blockchain, 2);
+        assert_eq!(db::history::get(&blockchain.db, &[1; 20]).unwrap().len(), 1);
+        assert_eq!(db::consistency::verify(&blockchain.db).unwrap(), Vec::<String>::new());
+        let hash = blockchain.hash_at_height(2).unwrap();
+        blockchain.db.delete_cf(db::block_heights(&blockchain.db), hash).unwrap();
+        assert_eq!(db::consistency::verify(&blockchain.db).unwrap().len(), 3);
+        assert_eq!(db::consistency::reindex(&blockchain.db).unwrap(), 5);
+        assert_eq!(db::consistency::verify(&blockchain.db).unwrap(), Vec::<String>::new());
+        assert_eq!(blockchain.hash_at_height(2), Some(hash));
+        let key = blockchain
+            .db
+            .iterator_cf(db::history(&blockchain.db), IteratorMode::Start)
+            .next()
+            .unwrap()
+            .unwrap()
+            .0;
+        blockchain.db.delete_cf(db::history(&blockchain.db), key).unwrap();
+        assert_eq!(db::consistency::verify(&blockchain.db).unwrap().len(), 1);
+        db::consistency::reindex(&blockchain.db).unwrap();
+        assert_eq!(db::consistency::verify(&blockchain.db).unwrap(), Vec::<String>::new());
+        assert_eq!(db::history::get(&blockchain.db, &[1; 20]).unwrap().len(), 1);
+    }
+}
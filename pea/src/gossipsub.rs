@@ -1,8 +1,7 @@
 use crate::util;
-use crate::{multiaddr, node::Node};
+use crate::{multiaddr, node::Node, spec::ChainSpec};
 use libp2p::{gossipsub::GossipsubMessage, Multiaddr, PeerId};
-use pea_block::BlockB;
-use pea_core::*;
+use pea_block::{BlockB, Checkpoint, Evidence};
 use pea_stake::StakeB;
 use pea_transaction::TransactionB;
 use std::collections::HashMap;
@@ -11,7 +10,8 @@ use std::net::IpAddr;
 pub fn handler(node: &mut Node, message: GossipsubMessage, propagation_source: PeerId) -> Result<(), Box<dyn Error>> {
     let (multiaddr, _) = node.p2p_connections.iter().find(|x| x.1 == &propagation_source).unwrap();
     let addr = multiaddr::addr(multiaddr).expect("multiaddr to include ip");
-    match message.topic.as_str() {
+    let prefix = node.blockchain.spec.topic("");
+    match message.topic.as_str().strip_prefix(&prefix).unwrap_or_default() {
         "block" => {
             ratelimit(node, addr, propagation_source, Topic::Block)?;
             let block_b: BlockB = bincode::deserialize(&message.data)?;
@@ -34,6 +34,16 @@ pub fn handler(node: &mut Node, message: GossipsubMessage, propagation_source: P
             let stake_b: StakeB = bincode::deserialize(&message.data)?;
             node.blockchain.pending_stakes_push(stake_b, util::timestamp())?;
         }
+        "evidence" => {
+            ratelimit(node, addr, propagation_source, Topic::Evidence)?;
+            let evidence: Evidence = bincode::deserialize(&message.data)?;
+            node.blockchain.pending_evidence_push(evidence)?;
+        }
+        "checkpoint" => {
+            ratelimit(node, addr, propagation_source, Topic::Checkpoint)?;
+            let checkpoint: Checkpoint = bincode::deserialize(&message.data)?;
+            node.blockchain.checkpoints_push(checkpoint)?;
+        }
         "multiaddr" => {
             ratelimit(node, addr, propagation_source, Topic::Multiaddr)?;
             for multiaddr in bincode::deserialize::<Vec<Multiaddr>>(&message.data)? {
@@ -59,21 +69,40 @@ pub enum Topic {
     Stake,
     Multiaddr,
     Blocks,
+    Evidence,
+    Checkpoint,
 }
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Ratelimit {
-    map: HashMap<IpAddr, ([usize; 5], Option<u32>)>,
+    map: HashMap<IpAddr, ([usize; 7], Option<u32>)>,
+    limits: [usize; 7],
+    duration: u32,
 }
 impl Ratelimit {
-    pub fn get(&self, addr: &IpAddr) -> ([usize; 5], Option<u32>) {
+    pub fn new(spec: &ChainSpec) -> Ratelimit {
+        Ratelimit {
+            map: HashMap::new(),
+            limits: [
+                spec.ratelimit_topic_block,
+                spec.ratelimit_topic_blocks,
+                spec.ratelimit_topic_transaction,
+                spec.ratelimit_topic_stake,
+                spec.ratelimit_topic_multiaddr,
+                spec.ratelimit_topic_evidence,
+                spec.ratelimit_topic_checkpoint,
+            ],
+            duration: spec.ratelimit_duration,
+        }
+    }
+    pub fn get(&self, addr: &IpAddr) -> ([usize; 7], Option<u32>) {
         match self.map.get(addr) {
             Some(x) => *x,
-            None => ([0; 5], None),
+            None => ([0; 7], None),
         }
     }
     pub fn is_ratelimited(&self, b: &Option<u32>) -> bool {
         if let Some(timestamp) = b {
-            if timestamp + RATELIMIT_DURATION > util::timestamp() {
+            if timestamp + self.duration > util::timestamp() {
                 return true;
             }
         }
@@ -89,23 +118,31 @@ impl Ratelimit {
         let ratelimited = match topic {
             Topic::Block => {
                 a[0] += 1;
-                a[0] > RATELIMIT_TOPIC_BLOCK
+                a[0] > self.limits[0]
             }
             Topic::Blocks => {
                 a[1] += 1;
-                a[1] > RATELIMIT_TOPIC_BLOCKS
+                a[1] > self.limits[1]
             }
             Topic::Transaction => {
                 a[2] += 1;
-                a[2] > RATELIMIT_TOPIC_TRANSACTION
+                a[2] > self.limits[2]
             }
             Topic::Stake => {
                 a[3] += 1;
-                a[3] > RATELIMIT_TOPIC_STAKE
+                a[3] > self.limits[3]
             }
             Topic::Multiaddr => {
                 a[4] += 1;
-                a[4] > RATELIMIT_TOPIC_MULTIADDR
+                a[4] > self.limits[4]
+            }
+            Topic::Evidence => {
+                a[5] += 1;
+                a[5] > self.limits[5]
+            }
+            Topic::Checkpoint => {
+                a[6] += 1;
+                a[6] > self.limits[6]
             }
         };
         if ratelimited {
@@ -117,11 +154,9 @@ impl Ratelimit {
     pub fn reset(&mut self) {
         for value in self.map.values_mut() {
             let a = &mut value.0;
-            a[0] = a[0].saturating_sub(RATELIMIT_TOPIC_BLOCK);
-            a[1] = a[1].saturating_sub(RATELIMIT_TOPIC_BLOCKS);
-            a[2] = a[2].saturating_sub(RATELIMIT_TOPIC_TRANSACTION);
-            a[3] = a[3].saturating_sub(RATELIMIT_TOPIC_STAKE);
-            a[4] = a[4].saturating_sub(RATELIMIT_TOPIC_MULTIADDR);
+            for (i, limit) in self.limits.iter().enumerate() {
+                a[i] = a[i].saturating_sub(*limit);
+            }
         }
     }
 }
@@ -3,7 +3,7 @@ use colored::*;
 use libp2p::{multiaddr::Protocol, Multiaddr};
 use log::{debug, info, warn};
 use pea_address::address;
-use pea_block::BlockA;
+use pea_block::{BlockA, Checkpoint};
 use pea_core::*;
 use std::time::Duration;
 fn delay(node: &mut Node, seconds: usize) -> bool {
@@ -16,6 +16,8 @@ pub fn handler(node: &mut Node, instant: tokio::time::Instant) {
     }
     if delay(node, 10) {
         share(node);
+        evidence(node);
+        checkpoint(node);
     }
     if delay(node, 5) {
         dial_unknown(node);
@@ -43,18 +45,14 @@ fn pending_blocks(node: &mut Node) {
         }
         vec.push(block);
     }
-    loop {
-        if let Some(block) = vec.iter().find(|&block_a| match node.blockchain.validate_block_1(&block_a) {
-            Ok(()) => true,
-            Err(err) => {
-                debug!("{}", err);
-                false
-            }
-        }) {
-            node.blockchain.accept_block(&block, false);
-        } else {
-            break;
+    while let Some(block) = vec.iter().find(|&block_a| match node.blockchain.validate_block_1(block_a) {
+        Ok(()) => true,
+        Err(err) => {
+            debug!("{}", err);
+            false
         }
+    }) {
+        node.blockchain.accept_block(block, false);
     }
 }
 fn offline_staker(node: &mut Node, timestamp: u32) {
@@ -68,7 +66,7 @@ fn offline_staker(node: &mut Node, timestamp: u32) {
         return;
     }
     let dynamic = &node.blockchain.states.dynamic;
-    for staker in dynamic.stakers_offline(timestamp, dynamic.latest_block.timestamp) {
+    for staker in dynamic.stakers_offline(&node.blockchain.spec, timestamp, dynamic.latest_block.timestamp) {
         if let Some(hash) = node.blockchain.offline.insert(staker, dynamic.latest_block.hash) {
             if hash == dynamic.latest_block.hash {
                 return;
@@ -115,8 +113,36 @@ fn share(node: &mut Node) {
     let vec: Vec<&Multiaddr> = node.p2p_connections.keys().collect();
     node.gossipsub_publish("multiaddr", bincode::serialize(&vec).unwrap());
 }
+fn evidence(node: &mut Node) {
+    if !node.gossipsub_has_mesh_peers("evidence") {
+        return;
+    }
+    for evidence in node.blockchain.pending_evidence.clone() {
+        node.gossipsub_publish("evidence", bincode::serialize(&evidence).unwrap());
+    }
+}
+fn checkpoint(node: &mut Node) {
+    if !node.blockchain.sync.completed {
+        return;
+    }
+    if !node.blockchain.states.dynamic.stakers.contains(&node.blockchain.key.address_bytes()) {
+        return;
+    }
+    let hash = match node.blockchain.checkpoint_hash() {
+        Some(x) => x,
+        None => return,
+    };
+    let checkpoint = Checkpoint::sign(node.blockchain.spec.chain_id, hash, &node.blockchain.key).unwrap();
+    if let Err(err) = node.blockchain.checkpoints_push(checkpoint.clone()) {
+        debug!("{}", err);
+    }
+    if !node.gossipsub_has_mesh_peers("checkpoint") {
+        return;
+    }
+    node.gossipsub_publish("checkpoint", bincode::serialize(&checkpoint).unwrap());
+}
 fn grow(node: &mut Node, timestamp: u32) {
-    if !node.blockchain.sync.downloading() && !node.mint && node.blockchain.states.dynamic.next_staker(timestamp).is_none() {
+    if !node.blockchain.sync.downloading() && !node.mint && node.blockchain.states.dynamic.next_staker(&node.blockchain.spec, timestamp).is_none() {
         if delay(node, 60) {
             info!(
                 "Waiting for synchronization to start... Currently connected to {} peers.",
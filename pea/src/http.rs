@@ -1,36 +1,81 @@
-use crate::{multiaddr, node::Node, util};
+use crate::{
+    mempool::{Entry, Status},
+    multiaddr,
+    node::Node,
+    util,
+};
 use chrono::{TimeZone, Utc};
 use libp2p::Multiaddr;
 use log::error;
-use pea_address::address;
+use pea_address::{address, multisig};
 use pea_api as api;
 use pea_core::*;
-use pea_db as db;
+use pea_db::{self as db, history::Direction};
 use pea_stake::StakeB;
-use pea_transaction::TransactionB;
+use pea_transaction::{Htlc, TransactionA, TransactionB};
 use std::{error::Error, io::BufRead, time::Duration};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
     net::TcpStream,
     time::timeout,
 };
-fn parse_body(buffer: &[u8; 1024]) -> Result<String, Box<dyn Error>> {
-    let str = std::str::from_utf8(buffer)?;
-    let vec = str.split("\n\n").collect::<Vec<&str>>();
-    let body = vec.get(1).ok_or("empty body")?;
-    Ok(body.trim_end_matches(char::from(0)).to_string())
+const BODY_LIMIT: usize = 0x20000;
+fn head_len(buffer: &[u8]) -> Option<usize> {
+    [&b"\r\n\r\n"[..], &b"\n\n"[..]]
+        .iter()
+        .filter_map(|x| buffer.windows(x.len()).position(|y| y == *x).map(|i| i + x.len()))
+        .min()
+}
+fn parse_content_length(head: &[u8]) -> Result<Option<usize>, Box<dyn Error>> {
+    for line in head.lines().skip(1) {
+        if let Some((key, value)) = line?.split_once(':') {
+            if key.trim().eq_ignore_ascii_case("content-length") {
+                return Ok(Some(value.trim().parse()?));
+            }
+        }
+    }
+    Ok(None)
+}
+fn parse_body(buffer: &[u8]) -> Result<String, Box<dyn Error>> {
+    let head_len = head_len(buffer).ok_or("empty body")?;
+    Ok(std::str::from_utf8(&buffer[head_len..])?.to_string())
 }
 fn parse_request_line(buffer: &[u8]) -> Result<String, Box<dyn Error>> {
     Ok(buffer.lines().next().ok_or("empty request line")??)
 }
+async fn read<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut buffer = vec![0; 1024];
+    let bytes = timeout(Duration::from_millis(1), reader.read(&mut buffer)).await??;
+    buffer.truncate(bytes);
+    let head_len = match head_len(&buffer) {
+        Some(x) => x,
+        None => return Ok(buffer),
+    };
+    if let Some(content_length) = parse_content_length(&buffer[..head_len])? {
+        if content_length > BODY_LIMIT {
+            return Err("body too large".into());
+        }
+        let len = head_len + content_length;
+        while buffer.len() < len {
+            let mut chunk = [0; 1024];
+            let bytes = timeout(Duration::from_secs(1), reader.read(&mut chunk)).await??;
+            if bytes == 0 {
+                return Err("body truncated".into());
+            }
+            buffer.extend_from_slice(&chunk[..bytes]);
+        }
+        buffer.truncate(len);
+    }
+    Ok(buffer)
+}
 pub async fn handler(mut stream: TcpStream, node: &mut Node) -> Result<(usize, String), Box<dyn Error>> {
-    let mut buffer = [0; 1024];
-    let bytes = timeout(Duration::from_millis(1), stream.read(&mut buffer)).await??;
+    let buffer = read(&mut stream).await?;
+    let bytes = buffer.len();
     let request_line = parse_request_line(&buffer)?;
     let vec: Vec<&str> = request_line.split(' ').collect();
-    let method = vec.get(0).ok_or("method")?;
+    let method = vec.first().ok_or("method")?;
     let path = vec.get(1).ok_or("path")?;
-    let args: Vec<&str> = path.split("/").filter(|&x| x != "").collect();
+    let args: Vec<&str> = path.split("/").filter(|&x| !x.is_empty()).collect();
     write(
         &mut stream,
         match *method {
@@ -44,10 +89,25 @@ pub async fn handler(mut stream: TcpStream, node: &mut Node) -> Result<(usize, S
     Ok((bytes, request_line))
 }
 fn get(node: &mut Node, args: Vec<&str>) -> Result<String, Box<dyn Error>> {
-    match args.get(0) {
+    match args.first() {
         Some(a) => match *a {
             "info" => get_info(node),
             "sync" => get_sync(node),
+            "finalized" => get_finalized(node),
+            "supply" => get_supply(node),
+            "pending" => match args.get(1) {
+                Some(&"transactions") => get_pending_transactions(node),
+                Some(&"stakes") => get_pending_stakes(node),
+                Some(&"blocks") => get_pending_blocks(node),
+                Some(&"transaction") => match args.get(2) {
+                    Some(b) => match hex::decode(b) {
+                        Ok(c) => get_pending_transaction_by_hash(node, c),
+                        Err(_) => c400(),
+                    },
+                    None => c400(),
+                },
+                _ => c404(),
+            },
             "dynamic" => get_dynamic(node),
             "trusted" => get_trusted(node),
             "options" => get_options(node),
@@ -65,6 +125,42 @@ fn get(node: &mut Node, args: Vec<&str>) -> Result<String, Box<dyn Error>> {
                 },
                 None => c400(),
             },
+            "unbonding" => match args.get(1) {
+                Some(b) => match address::decode(b) {
+                    Ok(c) => get_unbonding(node, c),
+                    Err(_) => c400(),
+                },
+                None => c400(),
+            },
+            "delegators" => match args.get(1) {
+                Some(b) => match address::decode(b) {
+                    Ok(c) => get_delegators(node, c),
+                    Err(_) => c400(),
+                },
+                None => c400(),
+            },
+            "delegations" => match args.get(1) {
+                Some(b) => match address::decode(b) {
+                    Ok(c) => get_delegations(node, c),
+                    Err(_) => c400(),
+                },
+                None => c400(),
+            },
+            "address" => match (
+                args.get(1).map(|x| address::decode(x)),
+                args.get(2).map(|x| x.split_once('?').unwrap_or((x, ""))),
+            ) {
+                (Some(Ok(b)), Some(("history", query))) => get_history(node, b, query),
+                (Some(Err(_)), _) => c400(),
+                _ => c404(),
+            },
+            "nonce" => match args.get(1) {
+                Some(b) => match address::decode(b) {
+                    Ok(c) => get_nonce(node, c),
+                    Err(_) => c400(),
+                },
+                None => c400(),
+            },
             "height" => match args.get(1) {
                 Some(b) => match hex::decode(b) {
                     Ok(c) => get_hash_height(node, c),
@@ -90,9 +186,11 @@ fn get(node: &mut Node, args: Vec<&str>) -> Result<String, Box<dyn Error>> {
                 None => c400(),
             },
             "transaction" => match args.get(1) {
-                Some(b) => match hex::decode(b) {
-                    Ok(c) => get_transaction_by_hash(node, c),
-                    Err(_) => c400(),
+                Some(b) => match (hex::decode(b), args.get(2)) {
+                    (Ok(c), None) => get_transaction_by_hash(node, c),
+                    (Ok(c), Some(&"status")) => get_transaction_status(node, c),
+                    (Ok(_), Some(_)) => c404(),
+                    (Err(_), _) => c400(),
                 },
                 None => c400(),
             },
@@ -103,6 +201,13 @@ fn get(node: &mut Node, args: Vec<&str>) -> Result<String, Box<dyn Error>> {
                 },
                 None => c400(),
             },
+            "lock" => match args.get(1) {
+                Some(b) => match hex::decode(b) {
+                    Ok(c) => get_lock(node, c),
+                    Err(_) => c400(),
+                },
+                None => c400(),
+            },
             "peer" => match args.get(1..) {
                 Some(b) => get_peer(node, b),
                 None => c400(),
@@ -114,7 +219,7 @@ fn get(node: &mut Node, args: Vec<&str>) -> Result<String, Box<dyn Error>> {
     }
 }
 fn post(node: &mut Node, args: Vec<&str>, body: String) -> Result<String, Box<dyn Error>> {
-    match args.get(0) {
+    match args.first() {
         Some(a) => match *a {
             "transaction" => post_transaction(node, body),
             "stake" => post_stake(node, body),
@@ -163,10 +268,14 @@ fn get_info(node: &mut Node) -> Result<String, Box<dyn Error>> {
     Ok(json(serde_json::to_string(&api::Info {
         time: Utc.timestamp_nanos(chrono::offset::Utc::now().timestamp_micros() * 1_000).to_rfc2822(),
         address: address::encode(&node.blockchain.key.address_bytes()),
-        uptime: format!("{}", node.uptime()),
+        uptime: node.uptime().to_string(),
         heartbeats: node.heartbeats,
         tree_size: node.blockchain.tree.size(),
         lag: node.lag,
+        pruned: match node.blockchain.pruned {
+            0 => None,
+            x => Some(api::Pruned { from: 0, to: x - 1 }),
+        },
     })?))
 }
 fn get_sync(node: &mut Node) -> Result<String, Box<dyn Error>> {
@@ -197,11 +306,14 @@ fn get_trusted(node: &mut Node) -> Result<String, Box<dyn Error>> {
 }
 fn get_options(node: &mut Node) -> Result<String, Box<dyn Error>> {
     Ok(json(serde_json::to_string(&api::Options {
+        chain: node.blockchain.spec.name.clone(),
+        chain_id: node.blockchain.spec.chain_id,
         mint: node.mint,
         trust: node.blockchain.trust_fork_after_blocks,
         pending: node.blockchain.pending_blocks_limit,
         ban_offline: node.p2p_ban_offline,
         time_delta: node.blockchain.time_delta,
+        prune: node.blockchain.prune,
         max_established: node.max_established,
         tps: node.tps,
         bind_api: node.bind_api.clone(),
@@ -219,13 +331,157 @@ fn get_staked(node: &mut Node, address_bytes: AddressBytes) -> Result<String, Bo
     let balance = node.blockchain.states.dynamic.staked(&address_bytes);
     Ok(json(serde_json::to_string(&pea_int::to_string(balance))?))
 }
+fn get_unbonding(node: &mut Node, address_bytes: AddressBytes) -> Result<String, Box<dyn Error>> {
+    let unbonding = node
+        .blockchain
+        .states
+        .dynamic
+        .unbonding(&address_bytes)
+        .into_iter()
+        .map(|(amount, blocks)| api::Unbonding {
+            amount: pea_int::to_string(amount),
+            blocks,
+        })
+        .collect::<Vec<api::Unbonding>>();
+    Ok(json(serde_json::to_string(&unbonding)?))
+}
+fn get_delegators(node: &mut Node, address_bytes: AddressBytes) -> Result<String, Box<dyn Error>> {
+    let delegations = node
+        .blockchain
+        .states
+        .dynamic
+        .delegators(&address_bytes)
+        .into_iter()
+        .map(|(delegator, amount)| api::Delegation {
+            staker: address::encode(&address_bytes),
+            delegator: address::encode(&delegator),
+            amount: pea_int::to_string(amount),
+        })
+        .collect::<Vec<api::Delegation>>();
+    Ok(json(serde_json::to_string(&delegations)?))
+}
+fn get_delegations(node: &mut Node, address_bytes: AddressBytes) -> Result<String, Box<dyn Error>> {
+    let delegations = node
+        .blockchain
+        .states
+        .dynamic
+        .delegations(&address_bytes)
+        .into_iter()
+        .map(|(staker, amount)| api::Delegation {
+            staker: address::encode(&staker),
+            delegator: address::encode(&address_bytes),
+            amount: pea_int::to_string(amount),
+        })
+        .collect::<Vec<api::Delegation>>();
+    Ok(json(serde_json::to_string(&delegations)?))
+}
+fn query_usize(query: &str, key: &str) -> Option<usize> {
+    query.split('&').find_map(|x| match x.split_once('=') {
+        Some((k, v)) if k == key => v.parse().ok(),
+        _ => None,
+    })
+}
+fn get_history(node: &mut Node, address_bytes: AddressBytes, query: &str) -> Result<String, Box<dyn Error>> {
+    let offset = query_usize(query, "offset").unwrap_or(0);
+    let limit = query_usize(query, "limit").unwrap_or(100).min(1_000);
+    let history = node
+        .blockchain
+        .history(&address_bytes, offset, limit)?
+        .into_iter()
+        .map(|x| api::History {
+            hash: hex::encode(x.hash),
+            block: hex::encode(x.block_hash),
+            height: x.height,
+            direction: match x.direction {
+                Direction::In => "in",
+                Direction::Out => "out",
+                Direction::Stake => "stake",
+            }
+            .to_string(),
+        })
+        .collect::<Vec<api::History>>();
+    Ok(json(serde_json::to_string(&history)?))
+}
+fn get_nonce(node: &mut Node, address_bytes: AddressBytes) -> Result<String, Box<dyn Error>> {
+    let nonce = node.blockchain.next_nonce(&address_bytes);
+    Ok(json(serde_json::to_string(&nonce)?))
+}
+fn pending<T>(entry: &Entry<T>, input_address: &AddressBytes, timestamp: u32) -> api::Pending {
+    api::Pending {
+        hash: hex::encode(entry.hash),
+        sender: address::encode(input_address),
+        fee: pea_int::to_string(entry.fee),
+        size: entry.size,
+        age: timestamp.saturating_sub(entry.received),
+    }
+}
+fn get_pending_transactions(node: &mut Node) -> Result<String, Box<dyn Error>> {
+    let timestamp = util::timestamp();
+    let pending = node
+        .blockchain
+        .mempool
+        .transactions
+        .iter()
+        .map(|x| pending(x, &x.item.input_address, timestamp))
+        .collect::<Vec<api::Pending>>();
+    Ok(json(serde_json::to_string(&pending)?))
+}
+fn get_pending_stakes(node: &mut Node) -> Result<String, Box<dyn Error>> {
+    let timestamp = util::timestamp();
+    let pending = node
+        .blockchain
+        .mempool
+        .stakes
+        .iter()
+        .map(|x| pending(x, &x.item.input_address, timestamp))
+        .collect::<Vec<api::Pending>>();
+    Ok(json(serde_json::to_string(&pending)?))
+}
+fn get_pending_blocks(node: &mut Node) -> Result<String, Box<dyn Error>> {
+    let timestamp = util::timestamp();
+    let pending = node
+        .blockchain
+        .pending_blocks
+        .iter()
+        .map(|x| api::Pending {
+            hash: hex::encode(x.hash),
+            sender: address::encode(&x.input_address()),
+            fee: pea_int::to_string(x.fees()),
+            size: bincode::serialize(&x.b()).unwrap().len(),
+            age: timestamp.saturating_sub(x.timestamp),
+        })
+        .collect::<Vec<api::Pending>>();
+    Ok(json(serde_json::to_string(&pending)?))
+}
+fn get_pending_transaction_by_hash(node: &mut Node, hash: Vec<u8>) -> Result<String, Box<dyn Error>> {
+    let transaction_a = node
+        .blockchain
+        .mempool
+        .transactions()
+        .find(|x| x.hash[..] == hash[..])
+        .ok_or("transaction not pending")?;
+    Ok(json(serde_json::to_string(&transaction(transaction_a, None))?))
+}
+fn get_supply(node: &mut Node) -> Result<String, Box<dyn Error>> {
+    let issuance = node.blockchain.states.dynamic.issuance();
+    Ok(json(serde_json::to_string(&api::Supply {
+        supply: pea_int::to_string(issuance.supply),
+        burned: pea_int::to_string(issuance.burned),
+    })?))
+}
+fn get_finalized(node: &mut Node) -> Result<String, Box<dyn Error>> {
+    let finalized = node.blockchain.finalized.map(|x| api::Finalized {
+        hash: hex::encode(x),
+        votes: node.blockchain.checkpoints.get(&x).map(|y| y.len()).unwrap_or_default(),
+    });
+    Ok(json(serde_json::to_string(&finalized)?))
+}
 fn get_height(node: &mut Node) -> Result<String, Box<dyn Error>> {
     let height = node.blockchain.height();
     Ok(json(serde_json::to_string(&height)?))
 }
 fn get_hash_height(node: &mut Node, hash: Vec<u8>) -> Result<String, Box<dyn Error>> {
-    let block_c = db::block::get_c(&node.blockchain.db, &hash)?;
-    let height = node.blockchain.tree.height(&block_c.previous_hash);
+    let height = db::height::get(&node.blockchain.db, &hash)?;
     Ok(json(serde_json::to_string(&height)?))
 }
 fn get_block_latest(node: &mut Node) -> Result<String, Box<dyn Error>> {
@@ -240,24 +496,18 @@ fn get_block_latest(node: &mut Node) -> Result<String, Box<dyn Error>> {
         beta: hex::encode(block_a.beta),
         transactions: block_a.transactions.iter().map(|x| hex::encode(x.hash)).collect(),
         stakes: block_a.stakes.iter().map(|x| hex::encode(x.hash)).collect(),
+        evidence: block_a.evidence.iter().map(|x| hex::encode(x.hash())).collect(),
     })?))
 }
 fn get_height_hash(node: &mut Node, height: usize) -> Result<String, Box<dyn Error>> {
-    let states = &node.blockchain.states;
-    let hashes_trusted = &states.trusted.hashes;
-    let hashes_dynamic = &states.dynamic.hashes;
-    if height >= hashes_trusted.len() + hashes_dynamic.len() {
-        return Err("GET HEIGHT_HASH".into());
-    }
-    let hash = if height < hashes_trusted.len() {
-        hashes_trusted[height]
-    } else {
-        hashes_dynamic[height - hashes_trusted.len()]
-    };
+    let hash = node.blockchain.hash_at_height(height).ok_or("GET HEIGHT_HASH")?;
     Ok(json(serde_json::to_string(&hex::encode(hash))?))
 }
 fn get_block_by_hash(node: &mut Node, hash: Vec<u8>) -> Result<String, Box<dyn Error>> {
-    let block_a = db::block::get_a(&node.blockchain.db, &hash)?;
+    let block_a = match db::block::get_a(&node.blockchain.db, &hash) {
+        Ok(x) => x,
+        Err(err) => return pruned(node, &hash, err),
+    };
     Ok(json(serde_json::to_string(&api::Block {
         hash: hex::encode(block_a.hash),
         previous_hash: hex::encode(block_a.previous_hash),
@@ -268,29 +518,117 @@ fn get_block_by_hash(node: &mut Node, hash: Vec<u8>) -> Result<String, Box<dyn E
         beta: hex::encode(block_a.beta),
         transactions: block_a.transactions.iter().map(|x| hex::encode(x.hash)).collect(),
         stakes: block_a.stakes.iter().map(|x| hex::encode(x.hash)).collect(),
+        evidence: block_a.evidence.iter().map(|x| hex::encode(x.hash())).collect(),
     })?))
 }
-fn get_transaction_by_hash(node: &mut Node, hash: Vec<u8>) -> Result<String, Box<dyn Error>> {
-    let transaction_a = db::transaction::get_a(&node.blockchain.db, &hash)?;
-    Ok(json(serde_json::to_string(&api::Transaction {
+fn transaction(transaction_a: &TransactionA, inclusion: Option<(Hash, usize, usize)>) -> api::Transaction {
+    api::Transaction {
         hash: hex::encode(transaction_a.hash),
+        nonce: transaction_a.nonce,
         input_address: address::encode(&transaction_a.input_address),
-        output_address: address::encode(&transaction_a.output_address),
-        amount: pea_int::to_string(transaction_a.amount),
+        outputs: transaction_a
+            .outputs
+            .iter()
+            .map(|(output_address, amount)| (address::encode(output_address), pea_int::to_string(*amount)))
+            .collect(),
+        amount: pea_int::to_string(transaction_a.amount()),
         fee: pea_int::to_string(transaction_a.fee),
+        memo: hex::encode(&transaction_a.memo),
+        multisig: transaction_a.multisig.as_ref().map(|x| multisig::encode(x.threshold, &x.addresses)),
         timestamp: transaction_a.timestamp,
         signature: hex::encode(transaction_a.signature),
+        block: inclusion.map(|x| hex::encode(x.0)),
+        height: inclusion.map(|x| x.1),
+        confirmations: inclusion.map(|x| x.2).unwrap_or_default(),
+    }
+}
+fn get_transaction_by_hash(node: &mut Node, hash: Vec<u8>) -> Result<String, Box<dyn Error>> {
+    let transaction_a = match db::transaction::get_a(&node.blockchain.db, &hash) {
+        Ok(x) => x,
+        Err(err) => return pruned(node, &hash, err),
+    };
+    let inclusion = node.blockchain.inclusion(&transaction_a.hash);
+    Ok(json(serde_json::to_string(&transaction(&transaction_a, inclusion))?))
+}
+fn get_transaction_status(node: &mut Node, hash: Vec<u8>) -> Result<String, Box<dyn Error>> {
+    let hash: Hash = hash.try_into().map_err(|_| "hash length")?;
+    let status = match node.blockchain.transaction_status(&hash) {
+        None => return c404(),
+        Some(Status::Pending) => api::TransactionStatus {
+            status: "pending".to_string(),
+            ..api::TransactionStatus::default()
+        },
+        Some(Status::Included { block_hash, height }) => api::TransactionStatus {
+            status: "included".to_string(),
+            block: Some(hex::encode(block_hash)),
+            height: Some(height),
+            ..api::TransactionStatus::default()
+        },
+        Some(Status::Replaced { hash }) => api::TransactionStatus {
+            status: "replaced".to_string(),
+            replaced_by: Some(hex::encode(hash)),
+            ..api::TransactionStatus::default()
+        },
+        Some(Status::Evicted { reason }) => api::TransactionStatus {
+            status: "evicted".to_string(),
+            reason: Some(reason),
+            ..api::TransactionStatus::default()
+        },
+        Some(Status::Rejected { reason }) => api::TransactionStatus {
+            status: "rejected".to_string(),
+            reason: Some(reason),
+            ..api::TransactionStatus::default()
+        },
+    };
+    Ok(json(serde_json::to_string(&status)?))
+}
+fn get_lock(node: &mut Node, hash: Vec<u8>) -> Result<String, Box<dyn Error>> {
+    let transaction_a = match db::transaction::get_a(&node.blockchain.db, &hash) {
+        Ok(x) => x,
+        Err(err) => return pruned(node, &hash, err),
+    };
+    let (hash_lock, timeout) = match transaction_a.htlc {
+        Some(Htlc::Lock { hash_lock, timeout }) => (hash_lock, timeout),
+        _ => return Err("transaction not a lock".into()),
+    };
+    let (recipient, amount) = transaction_a.outputs.first().ok_or("lock outputs")?;
+    let status = if node.blockchain.states.dynamic.lock(&transaction_a.hash).is_none() {
+        "released"
+    } else if util::timestamp() < timeout {
+        "locked"
+    } else {
+        "expired"
+    };
+    Ok(json(serde_json::to_string(&api::Lock {
+        hash: hex::encode(transaction_a.hash),
+        sender: address::encode(&transaction_a.input_address),
+        recipient: address::encode(recipient),
+        amount: pea_int::to_string(*amount),
+        hash_lock: hex::encode(hash_lock),
+        timeout,
+        status: status.to_string(),
     })?))
 }
 fn get_stake_by_hash(node: &mut Node, hash: Vec<u8>) -> Result<String, Box<dyn Error>> {
-    let stake_a = db::stake::get_a(&node.blockchain.db, &hash)?;
+    let stake_a = match db::stake::get_a(&node.blockchain.db, &hash) {
+        Ok(x) => x,
+        Err(err) => return pruned(node, &hash, err),
+    };
+    let inclusion = node.blockchain.inclusion(&stake_a.hash);
     Ok(json(serde_json::to_string(&api::Stake {
         hash: hex::encode(stake_a.hash),
+        version: stake_a.version,
         address: address::encode(&stake_a.input_address),
+        amount: pea_int::to_string(stake_a.amount),
         fee: pea_int::to_string(stake_a.fee),
         deposit: stake_a.deposit,
+        delegate: stake_a.delegate.map(|x| address::encode(&x)),
+        commission: stake_a.commission,
         timestamp: stake_a.timestamp,
         signature: hex::encode(stake_a.signature),
+        block: inclusion.map(|x| hex::encode(x.0)),
+        height: inclusion.map(|x| x.1),
+        confirmations: inclusion.map(|x| x.2).unwrap_or_default(),
     })?))
 }
 fn get_peers(node: &mut Node) -> Result<String, Box<dyn Error>> {
@@ -338,6 +676,12 @@ fn post_stake(node: &mut Node, body: String) -> Result<String, Box<dyn Error>> {
     };
     Ok(json(serde_json::to_string(&status)?))
 }
+fn pruned(node: &Node, hash: &[u8], err: Box<dyn Error>) -> Result<String, Box<dyn Error>> {
+    if node.blockchain.is_pruned(hash) {
+        return c410();
+    }
+    Err(err)
+}
 fn c400() -> Result<String, Box<dyn Error>> {
     Ok("HTTP/1.1 400 Bad Request".to_string())
 }
@@ -347,3 +691,75 @@ fn c404() -> Result<String, Box<dyn Error>> {
 fn c405() -> Result<String, Box<dyn Error>> {
     Ok("HTTP/1.1 405 Method Not Allowed".to_string())
 }
+fn c410() -> Result<String, Box<dyn Error>> {
+    Ok("HTTP/1.1 410 Gone\n\npruned".to_string())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::ChainSpec;
+    use pea_key::Key;
+    use pea_transaction::Multisig;
+    use tokio::net::TcpListener;
+    async fn roundtrip(transaction_b: &TransactionB) -> TransactionB {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let body = serde_json::to_string(transaction_b).unwrap();
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(format!("POST /transaction HTTP/1.1\nContent-Length: {}\n\n{}", body.len(), body).as_bytes())
+                .await
+                .unwrap();
+            stream
+        });
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let _stream = client.await.unwrap();
+        let buffer = read(&mut stream).await.unwrap();
+        assert!(buffer.len() > 1024);
+        assert_eq!(parse_request_line(&buffer).unwrap(), "POST /transaction HTTP/1.1");
+        serde_json::from_str(&parse_body(&buffer).unwrap()).unwrap()
+    }
+    #[tokio::test]
+    async fn test_post_batch() {
+        let spec = ChainSpec::mainnet();
+        let key = Key::generate();
+        let outputs = (0..spec.transaction_outputs_limit).map(|i| ([i as u8; 20], u128::MAX >> 32)).collect();
+        let transaction_a = TransactionA::sign_batch(
+            spec.chain_id,
+            u64::MAX,
+            outputs,
+            u128::MAX >> 32,
+            vec![u8::MAX; spec.transaction_memo_limit],
+            u32::MAX,
+            &key,
+        )
+        .unwrap();
+        assert_eq!(roundtrip(&transaction_a.b()).await.hash(), transaction_a.hash());
+    }
+    #[tokio::test]
+    async fn test_post_multisig() {
+        let spec = ChainSpec::mainnet();
+        let keys = [Key::generate(), Key::generate(), Key::generate()];
+        let addresses = keys.iter().map(|x| x.address_bytes()).collect::<Vec<_>>();
+        let mut transaction_a = TransactionA {
+            chain_id: spec.chain_id,
+            input_address: Key::multisig_address(2, &addresses),
+            outputs: vec![([0; 20], COIN)],
+            fee: COIN,
+            memo: vec![u8::MAX; spec.transaction_memo_limit],
+            multisig: Some(Multisig {
+                threshold: 2,
+                addresses,
+                signatures: vec![],
+            }),
+            ..TransactionA::default()
+        };
+        transaction_a.multisig_sign(&keys[0]).unwrap();
+        transaction_a.multisig_sign(&keys[2]).unwrap();
+        let transaction_b = roundtrip(&transaction_a.b()).await;
+        let multisig = transaction_b.multisig.as_ref().unwrap();
+        assert_eq!(multisig.signatures.len(), 2);
+        multisig.verify(&transaction_b.hash()).unwrap();
+    }
+}
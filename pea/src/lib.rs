@@ -3,8 +3,10 @@ pub mod blockchain;
 pub mod gossipsub;
 pub mod heartbeat;
 pub mod http;
+pub mod mempool;
 pub mod multiaddr;
 pub mod node;
+pub mod spec;
 pub mod state;
 pub mod states;
 pub mod sync;
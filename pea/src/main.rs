@@ -1,12 +1,21 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use colored::*;
-use log::{info, warn};
-use pea::node::{Node, Options};
+use log::{error, info, warn};
+use pea::{
+    blockchain::Blockchain,
+    node::{Node, Options},
+    spec::ChainSpec,
+};
+use pea_db as db;
+use pea_key::Key;
 use pea_logger as logger;
+use std::{error::Error, process};
+const CHAIN: &str = "mainnet";
 const TEMP_DB: bool = false;
 const TEMP_KEY: bool = false;
 const BIND_API: &str = ":::9332";
 const HOST: &str = "/ip4/0.0.0.0/tcp/9333";
+const DEV_CHAIN: &str = "devnet";
 const DEV_TEMP_DB: bool = true;
 const DEV_TEMP_KEY: bool = true;
 const DEV_BIND_API: &str = ":::9334";
@@ -38,9 +47,12 @@ pub struct Args {
     /// Mesh peers required to ban stakers that failed to show up
     #[clap(long, value_parser, default_value = "10")]
     pub ban_offline: usize,
-    /// Max time delta allowed
-    #[clap(long, value_parser, default_value = "1")]
-    pub time_delta: u32, // ping delay & perception of time
+    /// Max time delta allowed, defaults to the chain specification
+    #[clap(long, value_parser)]
+    pub time_delta: Option<u32>, // ping delay & perception of time
+    /// Keep transaction and stake bodies only for the last N trusted blocks
+    #[clap(long, value_parser)]
+    pub prune: Option<usize>,
     /// Swarm connection limits
     #[clap(long, value_parser)]
     pub max_established: Option<u32>,
@@ -62,12 +74,68 @@ pub struct Args {
     /// Multiaddr to listen on
     #[clap(short, long, value_parser, default_value = HOST)]
     pub host: String,
+    /// Chain specification preset (mainnet, testnet, devnet) or path to a chain specification file
+    #[clap(long, value_parser, default_value = CHAIN)]
+    pub chain: String,
     /// Development mode
     #[clap(long, value_parser, default_value_t = false)]
     pub dev: bool,
     /// Timeout
     #[clap(long, value_parser, default_value = "300")]
     pub timeout: u64,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Export the main chain to a bootstrap file
+    Export { path: String },
+    /// Import and validate blocks from a bootstrap file
+    Import { path: String },
+    /// Check the database for missing or inconsistent entries
+    VerifyDb {
+        /// Rebuild derived column families from raw blocks before checking
+        #[clap(long, value_parser, default_value_t = false)]
+        reindex: bool,
+    },
+}
+fn verify_db(reindex: bool, tempdb: bool) -> Result<(), Box<dyn Error>> {
+    let db = Node::db(tempdb);
+    if reindex {
+        let blocks = db::consistency::reindex(&db)?;
+        info!("Reindexed {} blocks", blocks.to_string().yellow());
+    }
+    let errors = db::consistency::verify(&db)?;
+    for err in errors.iter() {
+        error!("{}", err);
+    }
+    if !errors.is_empty() {
+        return Err(format!("found {} problems", errors.len()).into());
+    }
+    info!("{}", "Database is consistent".green());
+    Ok(())
+}
+fn resync(err: Box<dyn Error>) -> Box<dyn Error> {
+    format!("{}, remove the database and resync", err).into()
+}
+fn command(command: Command, args: &Args, spec: ChainSpec, time_delta: u32) -> Result<(), Box<dyn Error>> {
+    if let Command::VerifyDb { reindex } = command {
+        return verify_db(reindex, args.tempdb);
+    }
+    let mut blockchain = Blockchain::new(Node::db(args.tempdb), Key::generate(), args.trust, args.pending, time_delta, args.prune, spec);
+    blockchain.load().map_err(resync)?;
+    match command {
+        Command::Export { path } => {
+            let blocks = blockchain.export(&path)?;
+            info!("Exported {} blocks to {}", blocks.to_string().yellow(), path.magenta());
+        }
+        Command::Import { path } => {
+            let blocks = blockchain.import(&path)?;
+            info!("Imported {} blocks from {}", blocks.to_string().yellow(), path.magenta());
+        }
+        Command::VerifyDb { .. } => {}
+    }
+    Ok(())
 }
 #[tokio::main]
 async fn main() {
@@ -92,7 +160,12 @@ async fn main() {
         if args.host == HOST {
             args.host = DEV_HOST.to_string();
         }
+        if args.chain == CHAIN {
+            args.chain = DEV_CHAIN.to_string();
+        }
     }
+    let spec = ChainSpec::load(&args.chain).unwrap();
+    let time_delta = args.time_delta.unwrap_or(spec.time_delta);
     info!("{} {}", "--debug".cyan(), args.debug.to_string().magenta());
     info!("{} {}", "--tempdb".cyan(), args.tempdb.to_string().magenta());
     info!("{} {}", "--tempkey".cyan(), args.tempkey.to_string().magenta());
@@ -101,7 +174,8 @@ async fn main() {
     info!("{} {}", "--trust".cyan(), args.trust.to_string().magenta());
     info!("{} {}", "--pending".cyan(), args.pending.to_string().magenta());
     info!("{} {}", "--ban-offline".cyan(), args.ban_offline.to_string().magenta());
-    info!("{} {}", "--time-delta".cyan(), args.time_delta.to_string().magenta());
+    info!("{} {}", "--time-delta".cyan(), time_delta.to_string().magenta());
+    info!("{} {}", "--prune".cyan(), format!("{:?}", args.prune).magenta());
     info!("{} {}", "--max-established".cyan(), format!("{:?}", args.max_established).magenta());
     info!("{} {}", "--tps".cyan(), args.tps.to_string().magenta());
     info!("{} {}", "--wallet".cyan(), args.wallet.magenta());
@@ -109,10 +183,21 @@ async fn main() {
     info!("{} {}", "--peer".cyan(), args.peer.magenta());
     info!("{} {}", "--bind-api".cyan(), args.bind_api.magenta());
     info!("{} {}", "--host".cyan(), args.host.magenta());
+    info!("{} {}", "--chain".cyan(), args.chain.magenta());
     info!("{} {}", "--dev".cyan(), args.dev.to_string().magenta());
     if args.dev {
         warn!("{}", "DEVELOPMENT MODE IS ACTIVATED!".yellow());
     }
+    if args.prune.is_some() && spec.snapshot_interval == 0 {
+        warn!("{}", "Pruning requires a snapshot interval".yellow());
+    }
+    if let Some(x) = args.command.take() {
+        if let Err(err) = command(x, &args, spec, time_delta) {
+            error!("{}", err);
+            process::exit(1);
+        }
+        return;
+    }
     let mut node = Node::new(Options {
         tempdb: args.tempdb,
         tempkey: args.tempkey,
@@ -121,7 +206,8 @@ async fn main() {
         trust: args.trust,
         pending: args.pending,
         ban_offline: args.ban_offline,
-        time_delta: args.time_delta,
+        time_delta,
+        prune: args.prune,
         max_established: args.max_established,
         tps: args.tps,
         wallet: &args.wallet,
@@ -131,7 +217,11 @@ async fn main() {
         host: args.host,
         dev: args.dev,
         timeout: args.timeout,
+        spec,
     })
     .await;
-    node.start().await;
+    if let Err(err) = node.start().await.map_err(resync) {
+        error!("{}", err);
+        process::exit(1);
+    }
 }
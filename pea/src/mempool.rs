@@ -0,0 +1,273 @@
+use crate::spec::ChainSpec;
+use pea_core::*;
+use pea_db::pending::Pending;
+use pea_stake::StakeA;
+use pea_transaction::TransactionA;
+use std::{collections::VecDeque, error::Error};
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Pending,
+    Included { block_hash: Hash, height: usize },
+    Replaced { hash: Hash },
+    Evicted { reason: String },
+    Rejected { reason: String },
+}
+#[derive(Debug, Clone)]
+pub struct Entry<T> {
+    pub item: T,
+    pub hash: Hash,
+    pub fee: u128,
+    pub size: usize,
+    pub received: u32,
+}
+impl<T> Entry<T> {
+    pub fn fee_per_byte(&self) -> u128 {
+        self.fee / self.size.max(1) as u128
+    }
+    fn replaceable_by(&self, entry: &Entry<T>, bump: u128) -> bool {
+        entry.fee > self.fee && entry.fee_per_byte() * 100 >= self.fee_per_byte() * (100 + bump)
+    }
+}
+impl Entry<TransactionA> {
+    pub fn transaction(transaction_a: TransactionA, received: u32) -> Entry<TransactionA> {
+        Entry {
+            hash: transaction_a.hash,
+            fee: transaction_a.fee,
+            size: bincode::serialize(&transaction_a.b()).unwrap().len(),
+            received,
+            item: transaction_a,
+        }
+    }
+}
+impl Entry<StakeA> {
+    pub fn stake(stake_a: StakeA, received: u32) -> Entry<StakeA> {
+        Entry {
+            hash: stake_a.hash,
+            fee: stake_a.fee,
+            size: bincode::serialize(&stake_a.b()).unwrap().len(),
+            received,
+            item: stake_a,
+        }
+    }
+}
+#[derive(Debug, Default)]
+pub struct Mempool {
+    pub transactions: Vec<Entry<TransactionA>>,
+    pub stakes: Vec<Entry<StakeA>>,
+    statuses: VecDeque<(Hash, Status)>,
+    bytes: usize,
+}
+impl Mempool {
+    pub fn from(spec: &ChainSpec, (transactions, stakes): Pending) -> Mempool {
+        let mut mempool = Mempool {
+            transactions: transactions.into_iter().map(|(x, received)| Entry::transaction(x, received)).collect(),
+            stakes: stakes.into_iter().map(|(x, received)| Entry::stake(x, received)).collect(),
+            statuses: VecDeque::new(),
+            bytes: 0,
+        };
+        mempool.bytes = mempool.transactions.iter().map(|x| x.size).sum();
+        sort(&mut mempool.transactions);
+        sort(&mut mempool.stakes);
+        for hash in mempool.transactions.iter().map(|x| x.hash).collect::<Vec<_>>() {
+            mempool.set_status(spec, hash, Status::Pending);
+        }
+        mempool
+    }
+    pub fn entries(&self) -> Pending {
+        (
+            self.transactions.iter().map(|x| (x.item.clone(), x.received)).collect(),
+            self.stakes.iter().map(|x| (x.item.clone(), x.received)).collect(),
+        )
+    }
+    pub fn status(&self, hash: &Hash) -> Option<&Status> {
+        self.statuses.iter().find(|(x, _)| x == hash).map(|(_, status)| status)
+    }
+    pub fn set_status(&mut self, spec: &ChainSpec, hash: Hash, status: Status) {
+        self.statuses.retain(|(x, _)| x != &hash);
+        self.statuses.push_back((hash, status));
+        while self.statuses.len() > spec.pending_status_limit {
+            self.statuses.pop_front();
+        }
+    }
+    pub fn transactions(&self) -> impl Iterator<Item = &TransactionA> {
+        self.transactions.iter().map(|x| &x.item)
+    }
+    pub fn stakes(&self) -> impl Iterator<Item = &StakeA> {
+        self.stakes.iter().map(|x| &x.item)
+    }
+    pub fn transaction_push(&mut self, spec: &ChainSpec, entry: Entry<TransactionA>) -> Result<(), Box<dyn Error>> {
+        if self.transactions.iter().any(|x| x.hash == entry.hash) {
+            return Err("transaction pending".into());
+        }
+        if let Some(index) = self
+            .transactions
+            .iter()
+            .position(|x| x.item.input_address == entry.item.input_address && x.item.nonce == entry.item.nonce)
+        {
+            if !self.transactions[index].replaceable_by(&entry, spec.pending_replace_bump) {
+                return Err("transaction replacement fee too low".into());
+            }
+            let replaced = self.transactions.remove(index);
+            self.bytes -= replaced.size;
+            self.set_status(spec, replaced.hash, Status::Replaced { hash: entry.hash });
+        }
+        let hash = entry.hash;
+        self.bytes += entry.size;
+        let mut evicted = push(&mut self.transactions, entry, spec.pending_transactions_limit);
+        self.bytes -= evicted.iter().map(|x| x.size).sum::<usize>();
+        while self.bytes > spec.pending_transactions_bytes_limit {
+            match self.transactions.pop() {
+                Some(x) => {
+                    self.bytes -= x.size;
+                    evicted.push(x);
+                }
+                None => break,
+            }
+        }
+        for x in evicted.iter().filter(|x| x.hash != hash) {
+            self.set_status(
+                spec,
+                x.hash,
+                Status::Evicted {
+                    reason: "mempool full".to_string(),
+                },
+            );
+        }
+        if evicted.iter().any(|x| x.hash == hash) {
+            return Err("transaction fee too low".into());
+        }
+        self.set_status(spec, hash, Status::Pending);
+        Ok(())
+    }
+    pub fn stake_push(&mut self, spec: &ChainSpec, entry: Entry<StakeA>) -> Result<(), Box<dyn Error>> {
+        if self.stakes.iter().any(|x| x.hash == entry.hash) {
+            return Err("stake pending".into());
+        }
+        if let Some(index) = self.stakes.iter().position(|x| x.item.input_address == entry.item.input_address) {
+            if !self.stakes[index].replaceable_by(&entry, spec.pending_replace_bump) {
+                return Err("stake replacement fee too low".into());
+            }
+            self.stakes.remove(index);
+        }
+        let hash = entry.hash;
+        if push(&mut self.stakes, entry, spec.pending_stakes_limit).iter().any(|x| x.hash == hash) {
+            return Err("stake fee too low".into());
+        }
+        Ok(())
+    }
+    pub fn expire(&mut self, spec: &ChainSpec, timestamp: u32) {
+        let received = timestamp.saturating_sub(spec.pending_expiry);
+        let (transactions, expired) = std::mem::take(&mut self.transactions).into_iter().partition(|x| x.received >= received);
+        self.transactions = transactions;
+        for x in expired {
+            self.bytes -= x.size;
+            self.set_status(spec, x.hash, Status::Evicted { reason: "expired".to_string() });
+        }
+        self.stakes.retain(|x| x.received >= received);
+    }
+    pub fn retain_transactions<F: FnMut(&TransactionA) -> Result<(), Box<dyn Error>>>(&mut self, spec: &ChainSpec, mut f: F) {
+        let mut transactions = std::mem::take(&mut self.transactions);
+        transactions.sort_by_key(|x| x.item.nonce);
+        for x in transactions {
+            match f(&x.item) {
+                Ok(()) => self.transactions.push(x),
+                Err(err) => {
+                    self.bytes -= x.size;
+                    if !matches!(self.status(&x.hash), Some(Status::Included { .. })) {
+                        self.set_status(spec, x.hash, Status::Evicted { reason: err.to_string() });
+                    }
+                }
+            }
+        }
+        sort(&mut self.transactions);
+    }
+    pub fn retain_stakes<F: FnMut(&StakeA) -> bool>(&mut self, mut f: F) {
+        self.stakes.retain(|x| f(&x.item));
+    }
+}
+fn sort<T>(vec: &mut [Entry<T>]) {
+    vec.sort_by_key(|x| std::cmp::Reverse(x.fee_per_byte()));
+}
+fn push<T>(vec: &mut Vec<Entry<T>>, entry: Entry<T>, limit: usize) -> Vec<Entry<T>> {
+    vec.push(entry);
+    sort(vec);
+    let mut evicted = vec![];
+    while vec.len() > limit {
+        evicted.extend(vec.pop());
+    }
+    evicted
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn entry(nonce: u64, fee: u128, received: u32) -> Entry<TransactionA> {
+        let mut entry = Entry::transaction(
+            TransactionA {
+                nonce,
+                fee,
+                ..TransactionA::default()
+            },
+            received,
+        );
+        entry.hash = [nonce as u8 + fee as u8; 32];
+        entry
+    }
+    #[test]
+    fn test_replace() {
+        let spec = ChainSpec::devnet();
+        let mut mempool = Mempool::default();
+        mempool.transaction_push(&spec, entry(0, 1_000, 0)).unwrap();
+        assert!(mempool.transaction_push(&spec, entry(0, 1_001, 0)).is_err());
+        mempool.transaction_push(&spec, entry(0, 2_000, 0)).unwrap();
+        assert_eq!(mempool.transactions().map(|x| x.fee).collect::<Vec<_>>(), vec![2_000]);
+        assert_eq!(
+            mempool.status(&entry(0, 1_000, 0).hash),
+            Some(&Status::Replaced { hash: entry(0, 2_000, 0).hash })
+        );
+    }
+    #[test]
+    fn test_evict_bytes() {
+        let size = entry(0, 0, 0).size;
+        let spec = ChainSpec {
+            pending_transactions_bytes_limit: size * 2,
+            ..ChainSpec::devnet()
+        };
+        let mut mempool = Mempool::default();
+        mempool.transaction_push(&spec, entry(0, 1_000, 0)).unwrap();
+        mempool.transaction_push(&spec, entry(1, 3_000, 0)).unwrap();
+        mempool.transaction_push(&spec, entry(2, 2_000, spec.pending_expiry + 1)).unwrap();
+        assert_eq!(mempool.transactions().map(|x| x.fee).collect::<Vec<_>>(), vec![3_000, 2_000]);
+        assert_eq!(mempool.bytes, size * 2);
+        assert!(mempool.transaction_push(&spec, entry(3, 500, 0)).is_err());
+        assert_eq!(mempool.bytes, size * 2);
+        mempool.expire(&spec, spec.pending_expiry + 1);
+        assert_eq!(mempool.bytes, size);
+        mempool.retain_transactions(&spec, |_| Err("transaction nonce".into()));
+        assert_eq!(mempool.bytes, 0);
+    }
+    #[test]
+    fn test_evict() {
+        let spec = ChainSpec {
+            pending_transactions_limit: 2,
+            ..ChainSpec::devnet()
+        };
+        let mut mempool = Mempool::default();
+        mempool.transaction_push(&spec, entry(0, 1_000, 0)).unwrap();
+        mempool.transaction_push(&spec, entry(1, 3_000, 0)).unwrap();
+        assert!(mempool.transaction_push(&spec, entry(2, 500, 0)).is_err());
+        mempool.transaction_push(&spec, entry(3, 2_000, spec.pending_expiry + 1)).unwrap();
+        assert_eq!(mempool.transactions().map(|x| x.fee).collect::<Vec<_>>(), vec![3_000, 2_000]);
+        mempool.expire(&spec, spec.pending_expiry + 1);
+        assert_eq!(mempool.transactions().map(|x| x.fee).collect::<Vec<_>>(), vec![2_000]);
+        assert_eq!(
+            mempool.status(&entry(0, 1_000, 0).hash),
+            Some(&Status::Evicted {
+                reason: "mempool full".to_string()
+            })
+        );
+        assert_eq!(
+            mempool.status(&entry(1, 3_000, 0).hash),
+            Some(&Status::Evicted { reason: "expired".to_string() })
+        );
+    }
+}
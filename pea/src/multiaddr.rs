@@ -3,7 +3,7 @@ use std::net::IpAddr;
 pub fn filter_ip(multiaddr: &Multiaddr) -> Option<Multiaddr> {
     let components = multiaddr.iter().collect::<Vec<_>>();
     let mut multiaddr: Multiaddr = "".parse().unwrap();
-    match components.get(0) {
+    match components.first() {
         Some(Protocol::Ip4(ip)) => multiaddr.push(Protocol::Ip4(*ip)),
         Some(Protocol::Ip6(ip)) => multiaddr.push(Protocol::Ip6(*ip)),
         _ => return None,
@@ -13,7 +13,7 @@ pub fn filter_ip(multiaddr: &Multiaddr) -> Option<Multiaddr> {
 pub fn filter_ip_port(multiaddr: &Multiaddr) -> Option<Multiaddr> {
     let components = multiaddr.iter().collect::<Vec<_>>();
     let mut multiaddr: Multiaddr = "".parse().unwrap();
-    match components.get(0) {
+    match components.first() {
         Some(Protocol::Ip4(ip)) => multiaddr.push(Protocol::Ip4(*ip)),
         Some(Protocol::Ip6(ip)) => multiaddr.push(Protocol::Ip6(*ip)),
         _ => return None,
@@ -70,6 +70,7 @@ mod tests {
         );
     }
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_has_port() {
         assert_eq!(has_port(&"".parse::<Multiaddr>().unwrap()), false);
         assert_eq!(has_port(&"/ip4/0.0.0.0".parse::<Multiaddr>().unwrap()), false);
@@ -1,3 +1,4 @@
+use crate::spec::ChainSpec;
 use crate::{
     behaviour::{Behaviour, OutEvent},
     blockchain::Blockchain,
@@ -10,7 +11,7 @@ use libp2p::{
     core::{connection::ConnectedPoint, either::EitherError, upgrade},
     futures::StreamExt,
     gossipsub::{error::GossipsubHandlerError, GossipsubEvent, IdentTopic, TopicHash},
-    identity, mdns, mplex, noise,
+    identify, identity, mdns, mplex, noise,
     swarm::{ConnectionHandlerUpgrErr, ConnectionLimits, SwarmBuilder, SwarmEvent},
     tcp, Multiaddr, PeerId, Swarm, Transport,
 };
@@ -40,6 +41,7 @@ pub struct Options<'a> {
     pub pending: usize,
     pub ban_offline: usize,
     pub time_delta: u32,
+    pub prune: Option<usize>,
     pub max_established: Option<u32>,
     pub tps: f64,
     pub wallet: &'a str,
@@ -49,6 +51,7 @@ pub struct Options<'a> {
     pub host: String,
     pub dev: bool,
     pub timeout: u64,
+    pub spec: ChainSpec,
 }
 pub struct Node {
     pub p2p_swarm: Swarm<Behaviour>,
@@ -75,8 +78,9 @@ impl Node {
         let key = Node::key(options.tempkey, options.wallet, options.passphrase);
         info!("Address {}", address::encode(&key.address_bytes()).green());
         let db = Node::db(options.tempdb);
-        let blockchain = Blockchain::new(db, key, options.trust, options.pending, options.time_delta);
-        let p2p_swarm = Node::swarm(options.max_established, options.timeout).await.unwrap();
+        let p2p_swarm = Node::swarm(options.max_established, options.timeout, &options.spec).await.unwrap();
+        let p2p_ratelimit = Ratelimit::new(&options.spec);
+        let blockchain = Blockchain::new(db, key, options.trust, options.pending, options.time_delta, options.prune, options.spec);
         let p2p_known = Node::known(&blockchain.db, options.peer);
         Node {
             p2p_swarm,
@@ -96,10 +100,10 @@ impl Node {
             tempdb: options.tempdb,
             tempkey: options.tempkey,
             dev: options.dev,
-            p2p_ratelimit: Ratelimit::default(),
+            p2p_ratelimit,
         }
     }
-    fn db(tempdb: bool) -> DBWithThreadMode<SingleThreaded> {
+    pub fn db(tempdb: bool) -> DBWithThreadMode<SingleThreaded> {
         let tempdir = TempDir::new("peacash-db").unwrap();
         let path: &str = match tempdb {
             true => tempdir.path().to_str().unwrap(),
@@ -113,7 +117,7 @@ impl Node {
             false => pea_wallet::util::load(wallet, passphrase).unwrap().3,
         }
     }
-    async fn swarm(max_established: Option<u32>, timeout: u64) -> Result<Swarm<Behaviour>, Box<dyn Error>> {
+    async fn swarm(max_established: Option<u32>, timeout: u64, spec: &ChainSpec) -> Result<Swarm<Behaviour>, Box<dyn Error>> {
         let local_key = identity::Keypair::generate_ed25519();
         let local_peer_id = PeerId::from(local_key.public());
         info!("Peer id {}", local_peer_id.to_string().cyan());
@@ -123,13 +127,15 @@ impl Node {
             .multiplex(mplex::MplexConfig::new())
             .timeout(Duration::from_millis(timeout))
             .boxed();
-        let mut behaviour = Behaviour::new(local_key).await?;
+        let mut behaviour = Behaviour::new(local_key, spec.identify_protocol_version()).await?;
         for ident_topic in [
-            IdentTopic::new("block"),
-            IdentTopic::new("blocks"),
-            IdentTopic::new("stake"),
-            IdentTopic::new("transaction"),
-            IdentTopic::new("multiaddr"),
+            IdentTopic::new(spec.topic("block")),
+            IdentTopic::new(spec.topic("blocks")),
+            IdentTopic::new(spec.topic("stake")),
+            IdentTopic::new(spec.topic("transaction")),
+            IdentTopic::new(spec.topic("multiaddr")),
+            IdentTopic::new(spec.topic("evidence")),
+            IdentTopic::new(spec.topic("checkpoint")),
         ]
         .iter()
         {
@@ -188,6 +194,12 @@ impl Node {
                     }
                 }
             }
+            SwarmEvent::Behaviour(OutEvent::Identify(identify::Event::Received { peer_id, info }))
+                if info.protocol_version != self.blockchain.spec.identify_protocol_version() =>
+            {
+                warn!("Protocol mismatch {} {}", peer_id.to_string().cyan(), info.protocol_version.red());
+                let _ = self.p2p_swarm.disconnect_peer_id(peer_id);
+            }
             SwarmEvent::Behaviour(OutEvent::Gossipsub(GossipsubEvent::Message {
                 message, propagation_source, ..
             })) => {
@@ -201,8 +213,8 @@ impl Node {
             _ => {}
         }
     }
-    pub async fn start(&mut self) {
-        self.blockchain.load();
+    pub async fn start(&mut self) -> Result<(), Box<dyn Error>> {
+        self.blockchain.load()?;
         info!(
             "Blockchain height is {}",
             if let Some(main) = self.blockchain.tree.main() {
@@ -296,10 +308,12 @@ impl Node {
         }
     }
     pub fn gossipsub_has_mesh_peers(&mut self, topic: &str) -> bool {
+        let topic = self.blockchain.spec.topic(topic);
         self.p2p_swarm.behaviour().gossipsub.mesh_peers(&TopicHash::from_raw(topic)).count() != 0
     }
     pub fn gossipsub_publish(&mut self, topic: &str, data: Vec<u8>) {
         self.filter(&data, true);
+        let topic = self.blockchain.spec.topic(topic);
         if let Err(err) = self.p2p_swarm.behaviour_mut().gossipsub.publish(IdentTopic::new(topic), data) {
             error!("{}", err);
         }
@@ -327,7 +341,7 @@ impl Node {
         }
         let timestamp = self.blockchain.states.dynamic.latest_block.timestamp;
         let mut diff = util::timestamp().saturating_sub(timestamp) as f32;
-        diff /= BLOCK_TIME_MIN as f32;
+        diff /= self.blockchain.spec.block_time_min as f32;
         diff /= self.blockchain.sync.bps;
         let mut string = util::duration_to_string(diff as u32, completed);
         if string != completed {
@@ -0,0 +1,238 @@
+use pea_address::address;
+use pea_core::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error, fs::File, io::BufReader, path::Path};
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Genesis {
+    pub timestamp: u32,
+    pub balances: BTreeMap<String, u128>,
+    pub staked: BTreeMap<String, u128>,
+}
+impl Genesis {
+    pub fn balances(&self) -> Result<Vec<(AddressBytes, u128)>, Box<dyn Error>> {
+        decode(&self.balances)
+    }
+    pub fn staked(&self) -> Result<Vec<(AddressBytes, u128)>, Box<dyn Error>> {
+        decode(&self.staked)
+    }
+}
+fn decode(map: &BTreeMap<String, u128>) -> Result<Vec<(AddressBytes, u128)>, Box<dyn Error>> {
+    let mut vec = vec![];
+    for (address, amount) in map.iter() {
+        vec.push((address::decode(address)?, *amount));
+    }
+    Ok(vec)
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Emission {
+    Constant { reward: u128 },
+    Halving { reward: u128, interval: usize },
+    Decaying { reward: u128, percent: u8, interval: usize },
+    Capped { reward: u128, cap: u128 },
+}
+impl Emission {
+    pub fn reward(&self, height: usize, supply: u128) -> u128 {
+        match *self {
+            Emission::Constant { reward } => reward,
+            Emission::Halving { reward, interval: 0 } => reward,
+            Emission::Halving { reward, interval } => reward.checked_shr((height / interval) as u32).unwrap_or_default(),
+            Emission::Decaying { reward, interval: 0, .. } => reward,
+            Emission::Decaying { mut reward, percent, interval } => {
+                for _ in 0..height / interval {
+                    if reward == 0 {
+                        break;
+                    }
+                    reward = reward * (100 - percent.min(100) as u128) / 100;
+                }
+                reward
+            }
+            Emission::Capped { reward, cap } => reward.min(cap.saturating_sub(supply)),
+        }
+    }
+}
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChainSpec {
+    pub name: String,
+    pub chain_id: u32,
+    pub protocol_version: String,
+    pub block_time_min: u32,
+    pub time_delta: u32,
+    pub block_transactions_limit: usize,
+    pub block_stakes_limit: usize,
+    pub block_evidence_limit: usize,
+    pub transaction_outputs_limit: usize,
+    pub transaction_memo_limit: usize,
+    pub transaction_memo_fee_per_byte: u128,
+    pub multisig_addresses_limit: usize,
+    pub htlc_preimage_limit: usize,
+    pub pending_transactions_limit: usize,
+    pub pending_stakes_limit: usize,
+    pub pending_transactions_bytes_limit: usize,
+    pub pending_evidence_limit: usize,
+    pub pending_expiry: u32,
+    pub pending_replace_bump: u128,
+    pub pending_status_limit: usize,
+    pub emission: Emission,
+    pub fee_burn: u8,
+    pub stake_v1_height: usize,
+    pub unbonding_blocks: usize,
+    pub checkpoint_interval: usize,
+    pub snapshot_interval: usize,
+    pub snapshot_keep: usize,
+    pub ratelimit_topic_block: usize,
+    pub ratelimit_topic_blocks: usize,
+    pub ratelimit_topic_transaction: usize,
+    pub ratelimit_topic_stake: usize,
+    pub ratelimit_topic_multiaddr: usize,
+    pub ratelimit_topic_evidence: usize,
+    pub ratelimit_topic_checkpoint: usize,
+    pub ratelimit_duration: u32,
+    pub genesis: Genesis,
+}
+impl ChainSpec {
+    pub fn mainnet() -> ChainSpec {
+        ChainSpec {
+            name: "mainnet".to_string(),
+            chain_id: 1,
+            protocol_version: "peacash/1.0.0".to_string(),
+            block_time_min: 1,
+            time_delta: 1,
+            block_transactions_limit: 100,
+            block_stakes_limit: 1,
+            block_evidence_limit: 4,
+            transaction_outputs_limit: 256,
+            transaction_memo_limit: 64,
+            transaction_memo_fee_per_byte: COIN / 1_000,
+            multisig_addresses_limit: 16,
+            htlc_preimage_limit: 32,
+            pending_transactions_limit: 100,
+            pending_stakes_limit: 1,
+            pending_transactions_bytes_limit: 0x100000,
+            pending_evidence_limit: 16,
+            pending_expiry: 60 * 60,
+            pending_replace_bump: 10,
+            pending_status_limit: 1_000,
+            emission: Emission::Constant { reward: COIN },
+            fee_burn: 0,
+            stake_v1_height: 0,
+            unbonding_blocks: 100,
+            checkpoint_interval: 10,
+            snapshot_interval: 1_000,
+            snapshot_keep: 2,
+            ratelimit_topic_block: 100,
+            ratelimit_topic_blocks: 100,
+            ratelimit_topic_transaction: 100,
+            ratelimit_topic_stake: 100,
+            ratelimit_topic_multiaddr: 100,
+            ratelimit_topic_evidence: 100,
+            ratelimit_topic_checkpoint: 100,
+            ratelimit_duration: 60 * 60,
+            genesis: Genesis::default(),
+        }
+    }
+    pub fn testnet() -> ChainSpec {
+        ChainSpec {
+            name: "testnet".to_string(),
+            chain_id: 2,
+            ..ChainSpec::mainnet()
+        }
+    }
+    pub fn devnet() -> ChainSpec {
+        ChainSpec {
+            name: "devnet".to_string(),
+            chain_id: 3,
+            unbonding_blocks: 10,
+            snapshot_interval: 100,
+            ratelimit_topic_block: 1_000,
+            ratelimit_topic_blocks: 1_000,
+            ratelimit_topic_transaction: 1_000,
+            ratelimit_topic_stake: 1_000,
+            ratelimit_topic_multiaddr: 1_000,
+            ratelimit_topic_evidence: 1_000,
+            ratelimit_topic_checkpoint: 1_000,
+            ratelimit_duration: 60,
+            ..ChainSpec::mainnet()
+        }
+    }
+    pub fn preset(name: &str) -> Option<ChainSpec> {
+        match name {
+            "mainnet" => Some(ChainSpec::mainnet()),
+            "testnet" => Some(ChainSpec::testnet()),
+            "devnet" => Some(ChainSpec::devnet()),
+            _ => None,
+        }
+    }
+    pub fn from_file(path: impl AsRef<Path>) -> Result<ChainSpec, Box<dyn Error>> {
+        let file = File::open(path)?;
+        let spec: ChainSpec = serde_json::from_reader(BufReader::new(file))?;
+        spec.genesis.balances()?;
+        spec.genesis.staked()?;
+        if spec.fee_burn > 100 {
+            return Err("spec fee burn".into());
+        }
+        Ok(spec)
+    }
+    pub fn load(chain: &str) -> Result<ChainSpec, Box<dyn Error>> {
+        match ChainSpec::preset(chain) {
+            Some(spec) => Ok(spec),
+            None => ChainSpec::from_file(chain),
+        }
+    }
+    pub fn identify_protocol_version(&self) -> String {
+        format!("{}/{}", self.protocol_version, self.chain_id)
+    }
+    pub fn topic(&self, topic: &str) -> String {
+        format!("{}/{}", self.chain_id, topic)
+    }
+    pub fn block_time_max(&self) -> u32 {
+        self.block_time_min + self.time_delta
+    }
+}
+impl Default for ChainSpec {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_preset() {
+        assert_eq!(ChainSpec::load("devnet").unwrap(), ChainSpec::devnet());
+        assert!(ChainSpec::preset("unknown").is_none());
+        for name in ["mainnet", "testnet", "devnet"] {
+            assert_eq!(pea_wallet::util::chain_id(name).unwrap(), ChainSpec::preset(name).unwrap().chain_id);
+        }
+    }
+    #[test]
+    fn test_serde() {
+        let spec = ChainSpec::mainnet();
+        assert_eq!(serde_json::from_str::<ChainSpec>(&serde_json::to_string(&spec).unwrap()).unwrap(), spec);
+    }
+    #[test]
+    fn test_emission() {
+        assert_eq!(Emission::Constant { reward: COIN }.reward(1_000_000, 0), COIN);
+        let halving = Emission::Halving { reward: COIN, interval: 10 };
+        assert_eq!(halving.reward(9, 0), COIN);
+        assert_eq!(halving.reward(10, 0), COIN / 2);
+        assert_eq!(halving.reward(10 * 128, 0), 0);
+        let decaying = Emission::Decaying {
+            reward: COIN,
+            percent: 10,
+            interval: 10,
+        };
+        assert_eq!(decaying.reward(20, 0), COIN * 81 / 100);
+        let capped = Emission::Capped { reward: COIN, cap: 10 * COIN };
+        assert_eq!(capped.reward(0, 9 * COIN + 1), COIN - 1);
+        assert_eq!(capped.reward(0, 10 * COIN), 0);
+    }
+    #[test]
+    fn test_genesis() {
+        let mut genesis = Genesis::default();
+        genesis.balances.insert("0x0000000000000000000000000000000000000000de47c9b2".to_string(), COIN);
+        assert_eq!(genesis.balances().unwrap(), vec![([0; 20], COIN)]);
+        genesis.staked.insert("0x00".to_string(), COIN);
+        assert!(genesis.staked().is_err());
+    }
+}
@@ -1,13 +1,39 @@
-use crate::util;
+use crate::{spec::ChainSpec, util};
 use colored::Colorize;
 use log::warn;
 use pea_address::address;
 use pea_block::BlockA;
 use pea_core::*;
 use pea_db as db;
+use pea_transaction::Htlc;
 use rocksdb::{DBWithThreadMode, SingleThreaded};
-use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+};
 pub type Map = HashMap<AddressBytes, u128>;
+pub type MapNonce = HashMap<AddressBytes, u64>;
+pub type MapLocked = HashMap<Hash, Lock>;
+pub type Unbonding = VecDeque<(AddressBytes, u128, usize)>;
+pub type MapDelegation = HashMap<(AddressBytes, AddressBytes), u128>;
+pub type MapCommission = HashMap<AddressBytes, u8>;
+pub type CommissionChanges = VecDeque<(AddressBytes, u8, usize)>;
+pub type MapSlashed = HashMap<(AddressBytes, Hash), usize>;
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub struct Issuance {
+    pub height: usize,
+    pub supply: u128,
+    pub burned: u128,
+}
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Lock {
+    pub sender: AddressBytes,
+    pub recipient: AddressBytes,
+    pub amount: u128,
+    pub hash_lock: Hash,
+    pub timeout: u32,
+}
 pub trait State {
     fn get_hashes_mut(&mut self) -> &mut Vec<Hash>;
     fn get_stakers(&self) -> &VecDeque<AddressBytes>;
@@ -16,19 +42,47 @@ pub trait State {
     fn get_map_balance_mut(&mut self) -> &mut Map;
     fn get_map_staked(&self) -> &Map;
     fn get_map_staked_mut(&mut self) -> &mut Map;
+    fn get_map_nonce(&self) -> &MapNonce;
+    fn get_map_nonce_mut(&mut self) -> &mut MapNonce;
+    fn get_map_locked(&self) -> &MapLocked;
+    fn get_map_locked_mut(&mut self) -> &mut MapLocked;
+    fn get_unbonding(&self) -> &Unbonding;
+    fn get_unbonding_mut(&mut self) -> &mut Unbonding;
+    fn get_map_delegation(&self) -> &MapDelegation;
+    fn get_map_delegation_mut(&mut self) -> &mut MapDelegation;
+    fn get_map_delegated(&self) -> &Map;
+    fn get_map_delegated_mut(&mut self) -> &mut Map;
+    fn get_map_commission(&self) -> &MapCommission;
+    fn get_map_commission_mut(&mut self) -> &mut MapCommission;
+    fn get_commission_changes(&self) -> &CommissionChanges;
+    fn get_commission_changes_mut(&mut self) -> &mut CommissionChanges;
+    fn get_map_slashed(&self) -> &MapSlashed;
+    fn get_map_slashed_mut(&mut self) -> &mut MapSlashed;
+    fn get_issuance(&self) -> &Issuance;
+    fn get_issuance_mut(&mut self) -> &mut Issuance;
     fn get_latest_block(&self) -> &BlockA;
     fn get_latest_block_mut(&mut self) -> &mut BlockA;
     fn is_trusted() -> bool;
-    fn append_block(&mut self, db: &DBWithThreadMode<SingleThreaded>, block: &BlockA, previous_timestamp: u32, loading: bool);
-    fn load(&mut self, db: &DBWithThreadMode<SingleThreaded>, hashes: &[Hash]);
+    fn append_block(&mut self, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, block: &BlockA, previous_timestamp: u32, loading: bool);
+    fn load(&mut self, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, hashes: &[Hash]);
 }
-#[derive(Default, Debug, Clone)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct Trusted {
     pub latest_block: BlockA,
+    #[serde(skip)]
     pub hashes: Vec<Hash>,
     pub stakers: VecDeque<AddressBytes>,
     map_balance: Map,
     map_staked: Map,
+    map_nonce: MapNonce,
+    map_locked: MapLocked,
+    unbonding: Unbonding,
+    map_delegation: MapDelegation,
+    map_delegated: Map,
+    map_commission: MapCommission,
+    commission_changes: CommissionChanges,
+    map_slashed: MapSlashed,
+    issuance: Issuance,
 }
 #[derive(Default, Debug, Clone)]
 pub struct Dynamic {
@@ -37,38 +91,116 @@ pub struct Dynamic {
     pub stakers: VecDeque<AddressBytes>,
     map_balance: Map,
     map_staked: Map,
+    map_nonce: MapNonce,
+    map_locked: MapLocked,
+    unbonding: Unbonding,
+    map_delegation: MapDelegation,
+    map_delegated: Map,
+    map_commission: MapCommission,
+    commission_changes: CommissionChanges,
+    map_slashed: MapSlashed,
+    issuance: Issuance,
 }
 impl Trusted {
-    pub fn append_block(&mut self, db: &DBWithThreadMode<SingleThreaded>, block: &BlockA, previous_timestamp: u32) {
-        append_block(self, db, block, previous_timestamp, false)
+    pub fn genesis(spec: &ChainSpec) -> Trusted {
+        genesis(spec)
+    }
+    pub fn append_block(&mut self, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, block: &BlockA, previous_timestamp: u32) {
+        append_block(self, db, spec, block, previous_timestamp, false)
     }
-    pub fn load(&mut self, db: &DBWithThreadMode<SingleThreaded>, hashes: &[Hash]) {
-        load(self, db, hashes)
+    pub fn load(&mut self, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, hashes: &[Hash]) {
+        load(self, db, spec, hashes)
+    }
+    pub fn snapshot(&self, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec) -> Result<(), Box<dyn Error>> {
+        db::snapshot::put(self.hashes.len(), &self.latest_block.hash, &bincode::serialize(self)?, spec.snapshot_keep, db)
+    }
+    pub fn restore(db: &DBWithThreadMode<SingleThreaded>, hashes: &[Hash]) -> Result<Option<Trusted>, Box<dyn Error>> {
+        for (height, hash, bytes) in db::snapshot::get(db)? {
+            if height == 0 || height > hashes.len() || hashes[height - 1] != hash {
+                continue;
+            }
+            let mut trusted: Trusted = bincode::deserialize(&bytes)?;
+            trusted.hashes = hashes[..height].to_vec();
+            return Ok(Some(trusted));
+        }
+        Ok(None)
     }
 }
 impl Dynamic {
+    pub fn genesis(spec: &ChainSpec) -> Dynamic {
+        genesis(spec)
+    }
     pub fn balance(&self, address: &AddressBytes) -> u128 {
         get_balance(self, address)
     }
     pub fn staked(&self, address: &AddressBytes) -> u128 {
         get_staked(self, address)
     }
-    pub fn from(db: &DBWithThreadMode<SingleThreaded>, hashes: &[Hash], trusted: &Trusted) -> Dynamic {
+    pub fn nonce(&self, address: &AddressBytes) -> u64 {
+        get_nonce(self, address)
+    }
+    pub fn lock(&self, hash: &Hash) -> Option<&Lock> {
+        self.map_locked.get(hash)
+    }
+    pub fn unbonding(&self, address: &AddressBytes) -> Vec<(u128, usize)> {
+        get_unbonding(self, address)
+    }
+    pub fn delegation(&self, delegator: &AddressBytes, staker: &AddressBytes) -> u128 {
+        get_delegation(self, delegator, staker)
+    }
+    pub fn issuance(&self) -> Issuance {
+        self.issuance
+    }
+    pub fn weight(&self, staker: &AddressBytes) -> u128 {
+        get_weight(self, staker)
+    }
+    pub fn delegated(&self, staker: &AddressBytes) -> u128 {
+        get_delegated(self, staker)
+    }
+    pub fn commission(&self, staker: &AddressBytes) -> u8 {
+        get_commission(self, staker)
+    }
+    pub fn slashed(&self, offender: &AddressBytes, previous_hash: &Hash) -> bool {
+        self.map_slashed.contains_key(&(*offender, *previous_hash))
+    }
+    pub fn delegators(&self, staker: &AddressBytes) -> Vec<(AddressBytes, u128)> {
+        get_delegators(self, staker)
+    }
+    pub fn delegations(&self, delegator: &AddressBytes) -> Vec<(AddressBytes, u128)> {
+        let mut vec = self
+            .map_delegation
+            .iter()
+            .filter(|((x, _), _)| x == delegator)
+            .map(|((_, staker), amount)| (*staker, *amount))
+            .collect::<Vec<_>>();
+        vec.sort();
+        vec
+    }
+    pub fn from(db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, hashes: &[Hash], trusted: &Trusted) -> Dynamic {
         let mut dynamic = Self {
             hashes: vec![],
             stakers: trusted.stakers.clone(),
             map_balance: trusted.map_balance.clone(),
             map_staked: trusted.map_staked.clone(),
-            latest_block: BlockA::default(),
+            map_nonce: trusted.map_nonce.clone(),
+            map_locked: trusted.map_locked.clone(),
+            unbonding: trusted.unbonding.clone(),
+            map_delegation: trusted.map_delegation.clone(),
+            map_delegated: trusted.map_delegated.clone(),
+            map_commission: trusted.map_commission.clone(),
+            commission_changes: trusted.commission_changes.clone(),
+            map_slashed: trusted.map_slashed.clone(),
+            issuance: trusted.issuance,
+            latest_block: trusted.latest_block.clone(),
         };
-        dynamic.load(db, hashes);
+        dynamic.load(db, spec, hashes);
         dynamic
     }
-    pub fn next_staker(&self, timestamp: u32) -> Option<AddressBytes> {
-        next_staker(self, timestamp)
+    pub fn next_staker(&self, spec: &ChainSpec, timestamp: u32) -> Option<AddressBytes> {
+        next_staker(self, spec, timestamp)
     }
-    pub fn stakers_offline(&self, timestamp: u32, previous_timestamp: u32) -> Vec<AddressBytes> {
-        stakers_offline(self, timestamp, previous_timestamp)
+    pub fn stakers_offline(&self, spec: &ChainSpec, timestamp: u32, previous_timestamp: u32) -> Vec<AddressBytes> {
+        stakers_offline(self, spec, timestamp, previous_timestamp)
     }
     pub fn stakers_n(&self, n: usize) -> Vec<AddressBytes> {
         stakers_n(self, n).0
@@ -96,6 +228,60 @@ impl State for Trusted {
     fn get_map_staked_mut(&mut self) -> &mut Map {
         &mut self.map_staked
     }
+    fn get_map_nonce(&self) -> &MapNonce {
+        &self.map_nonce
+    }
+    fn get_map_nonce_mut(&mut self) -> &mut MapNonce {
+        &mut self.map_nonce
+    }
+    fn get_map_locked(&self) -> &MapLocked {
+        &self.map_locked
+    }
+    fn get_map_locked_mut(&mut self) -> &mut MapLocked {
+        &mut self.map_locked
+    }
+    fn get_unbonding(&self) -> &Unbonding {
+        &self.unbonding
+    }
+    fn get_unbonding_mut(&mut self) -> &mut Unbonding {
+        &mut self.unbonding
+    }
+    fn get_map_delegation(&self) -> &MapDelegation {
+        &self.map_delegation
+    }
+    fn get_map_delegation_mut(&mut self) -> &mut MapDelegation {
+        &mut self.map_delegation
+    }
+    fn get_map_delegated(&self) -> &Map {
+        &self.map_delegated
+    }
+    fn get_map_delegated_mut(&mut self) -> &mut Map {
+        &mut self.map_delegated
+    }
+    fn get_map_commission(&self) -> &MapCommission {
+        &self.map_commission
+    }
+    fn get_map_commission_mut(&mut self) -> &mut MapCommission {
+        &mut self.map_commission
+    }
+    fn get_commission_changes(&self) -> &CommissionChanges {
+        &self.commission_changes
+    }
+    fn get_commission_changes_mut(&mut self) -> &mut CommissionChanges {
+        &mut self.commission_changes
+    }
+    fn get_map_slashed(&self) -> &MapSlashed {
+        &self.map_slashed
+    }
+    fn get_map_slashed_mut(&mut self) -> &mut MapSlashed {
+        &mut self.map_slashed
+    }
+    fn get_issuance(&self) -> &Issuance {
+        &self.issuance
+    }
+    fn get_issuance_mut(&mut self) -> &mut Issuance {
+        &mut self.issuance
+    }
     fn get_latest_block(&self) -> &BlockA {
         &self.latest_block
     }
@@ -105,11 +291,11 @@ impl State for Trusted {
     fn is_trusted() -> bool {
         true
     }
-    fn append_block(&mut self, db: &DBWithThreadMode<SingleThreaded>, block: &BlockA, previous_timestamp: u32, loading: bool) {
-        append_block(self, db, block, previous_timestamp, loading)
+    fn append_block(&mut self, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, block: &BlockA, previous_timestamp: u32, loading: bool) {
+        append_block(self, db, spec, block, previous_timestamp, loading)
     }
-    fn load(&mut self, db: &DBWithThreadMode<SingleThreaded>, hashes: &[Hash]) {
-        load(self, db, hashes)
+    fn load(&mut self, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, hashes: &[Hash]) {
+        load(self, db, spec, hashes)
     }
 }
 impl State for Dynamic {
@@ -134,6 +320,60 @@ impl State for Dynamic {
     fn get_map_staked_mut(&mut self) -> &mut Map {
         &mut self.map_staked
     }
+    fn get_map_nonce(&self) -> &MapNonce {
+        &self.map_nonce
+    }
+    fn get_map_nonce_mut(&mut self) -> &mut MapNonce {
+        &mut self.map_nonce
+    }
+    fn get_map_locked(&self) -> &MapLocked {
+        &self.map_locked
+    }
+    fn get_map_locked_mut(&mut self) -> &mut MapLocked {
+        &mut self.map_locked
+    }
+    fn get_unbonding(&self) -> &Unbonding {
+        &self.unbonding
+    }
+    fn get_unbonding_mut(&mut self) -> &mut Unbonding {
+        &mut self.unbonding
+    }
+    fn get_map_delegation(&self) -> &MapDelegation {
+        &self.map_delegation
+    }
+    fn get_map_delegation_mut(&mut self) -> &mut MapDelegation {
+        &mut self.map_delegation
+    }
+    fn get_map_delegated(&self) -> &Map {
+        &self.map_delegated
+    }
+    fn get_map_delegated_mut(&mut self) -> &mut Map {
+        &mut self.map_delegated
+    }
+    fn get_map_commission(&self) -> &MapCommission {
+        &self.map_commission
+    }
+    fn get_map_commission_mut(&mut self) -> &mut MapCommission {
+        &mut self.map_commission
+    }
+    fn get_commission_changes(&self) -> &CommissionChanges {
+        &self.commission_changes
+    }
+    fn get_commission_changes_mut(&mut self) -> &mut CommissionChanges {
+        &mut self.commission_changes
+    }
+    fn get_map_slashed(&self) -> &MapSlashed {
+        &self.map_slashed
+    }
+    fn get_map_slashed_mut(&mut self) -> &mut MapSlashed {
+        &mut self.map_slashed
+    }
+    fn get_issuance(&self) -> &Issuance {
+        &self.issuance
+    }
+    fn get_issuance_mut(&mut self) -> &mut Issuance {
+        &mut self.issuance
+    }
     fn get_latest_block(&self) -> &BlockA {
         &self.latest_block
     }
@@ -143,11 +383,11 @@ impl State for Dynamic {
     fn is_trusted() -> bool {
         false
     }
-    fn append_block(&mut self, db: &DBWithThreadMode<SingleThreaded>, block: &BlockA, previous_timestamp: u32, loading: bool) {
-        append_block(self, db, block, previous_timestamp, loading)
+    fn append_block(&mut self, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, block: &BlockA, previous_timestamp: u32, loading: bool) {
+        append_block(self, db, spec, block, previous_timestamp, loading)
     }
-    fn load(&mut self, db: &DBWithThreadMode<SingleThreaded>, hashes: &[Hash]) {
-        load(self, db, hashes)
+    fn load(&mut self, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, hashes: &[Hash]) {
+        load(self, db, spec, hashes)
     }
 }
 fn get_balance<T: State>(state: &T, address: &AddressBytes) -> u128 {
@@ -162,6 +402,51 @@ fn get_staked<T: State>(state: &T, address: &AddressBytes) -> u128 {
         None => 0,
     }
 }
+fn get_nonce<T: State>(state: &T, address: &AddressBytes) -> u64 {
+    match state.get_map_nonce().get(address) {
+        Some(n) => *n,
+        None => 0,
+    }
+}
+fn get_unbonding<T: State>(state: &T, address: &AddressBytes) -> Vec<(u128, usize)> {
+    state
+        .get_unbonding()
+        .iter()
+        .filter(|(x, _, _)| x == address)
+        .map(|(_, amount, blocks)| (*amount, *blocks))
+        .collect()
+}
+fn get_delegation<T: State>(state: &T, delegator: &AddressBytes, staker: &AddressBytes) -> u128 {
+    match state.get_map_delegation().get(&(*delegator, *staker)) {
+        Some(b) => *b,
+        None => 0,
+    }
+}
+fn get_delegated<T: State>(state: &T, staker: &AddressBytes) -> u128 {
+    match state.get_map_delegated().get(staker) {
+        Some(b) => *b,
+        None => 0,
+    }
+}
+fn get_weight<T: State>(state: &T, staker: &AddressBytes) -> u128 {
+    get_staked(state, staker) + get_delegated(state, staker)
+}
+fn get_commission<T: State>(state: &T, staker: &AddressBytes) -> u8 {
+    match state.get_map_commission().get(staker) {
+        Some(c) => *c,
+        None => 0,
+    }
+}
+fn get_delegators<T: State>(state: &T, staker: &AddressBytes) -> Vec<(AddressBytes, u128)> {
+    let mut vec = state
+        .get_map_delegation()
+        .iter()
+        .filter(|((_, x), _)| x == staker)
+        .map(|((delegator, _), amount)| (*delegator, *amount))
+        .collect::<Vec<_>>();
+    vec.sort();
+    vec
+}
 fn insert_balance<T: State>(state: &mut T, address: AddressBytes, balance: u128) {
     match balance {
         0 => state.get_map_balance_mut().remove(&address),
@@ -174,6 +459,36 @@ fn insert_staked<T: State>(state: &mut T, address: AddressBytes, staked: u128) {
         x => state.get_map_staked_mut().insert(address, x),
     };
 }
+fn insert_delegation<T: State>(state: &mut T, delegator: AddressBytes, staker: AddressBytes, amount: u128) {
+    let delegated = get_delegated(state, &staker) - get_delegation(state, &delegator, &staker) + amount;
+    match amount {
+        0 => state.get_map_delegation_mut().remove(&(delegator, staker)),
+        x => state.get_map_delegation_mut().insert((delegator, staker), x),
+    };
+    match delegated {
+        0 => state.get_map_delegated_mut().remove(&staker),
+        x => state.get_map_delegated_mut().insert(staker, x),
+    };
+}
+fn insert_commission<T: State>(state: &mut T, staker: AddressBytes, commission: u8) {
+    match commission {
+        0 => state.get_map_commission_mut().remove(&staker),
+        x => state.get_map_commission_mut().insert(staker, x),
+    };
+}
+fn update_commission<T: State>(state: &mut T, spec: &ChainSpec, staker: AddressBytes, commission: u8) {
+    state.get_commission_changes_mut().retain(|(x, _, _)| x != &staker);
+    if commission <= get_commission(state, &staker) || spec.unbonding_blocks == 0 {
+        insert_commission(state, staker, commission);
+    } else {
+        state.get_commission_changes_mut().push_back((staker, commission, spec.unbonding_blocks));
+    }
+}
+fn burn<T: State>(state: &mut T, amount: u128) {
+    let issuance = state.get_issuance_mut();
+    issuance.supply -= amount;
+    issuance.burned += amount;
+}
 fn update_stakers<T: State>(state: &mut T, address: AddressBytes) {
     let staked = get_staked(state, &address);
     let index = state.get_stakers().iter().position(|x| x == &address);
@@ -183,12 +498,12 @@ fn update_stakers<T: State>(state: &mut T, address: AddressBytes) {
         state.get_stakers_mut().remove(index.unwrap()).unwrap();
     }
 }
-fn update_0<T: State>(state: &mut T, timestamp: u32, previous_timestamp: u32, loading: bool) {
-    let stakers = stakers_offline(state, timestamp, previous_timestamp);
-    for index in 0..stakers.len() {
-        let staker = stakers[index];
+fn update_0<T: State>(state: &mut T, spec: &ChainSpec, block: &BlockA, previous_timestamp: u32, loading: bool) {
+    let stakers = stakers_offline(state, spec, block.timestamp, previous_timestamp);
+    for (index, staker) in stakers.into_iter().enumerate() {
         let mut staked = get_staked(state, &staker);
         let penalty = util::penalty(index + 1);
+        burn(state, staked.min(penalty));
         staked = staked.saturating_sub(penalty);
         insert_staked(state, staker, staked);
         update_stakers(state, staker);
@@ -202,39 +517,144 @@ fn update_0<T: State>(state: &mut T, timestamp: u32, previous_timestamp: u32, lo
             );
         }
     }
+    let height = state.get_issuance().height;
+    state.get_map_slashed_mut().retain(|_, x| *x + spec.unbonding_blocks >= height);
+    for evidence in block.evidence.iter() {
+        let staker = match evidence.input_address() {
+            Ok(x) => x,
+            Err(_) => continue,
+        };
+        state.get_map_slashed_mut().insert((staker, evidence.block_c_0.previous_hash), height);
+        let mut staked = get_staked(state, &staker) + get_unbonding(state, &staker).iter().map(|(amount, _)| amount).sum::<u128>();
+        for (delegator, amount) in get_delegators(state, &staker) {
+            insert_delegation(state, delegator, staker, 0);
+            staked += amount;
+        }
+        insert_staked(state, staker, 0);
+        update_stakers(state, staker);
+        burn(state, staked);
+        state.get_unbonding_mut().retain(|(address, _, _)| address != &staker);
+        if !loading && !T::is_trusted() {
+            warn!(
+                "{} {} {}{}",
+                "Slashed equivocation".red(),
+                address::encode(&staker).green(),
+                "-".yellow(),
+                pea_int::to_string(staked).yellow()
+            );
+        }
+    }
+    for (_, _, blocks) in state.get_unbonding_mut().iter_mut() {
+        *blocks = blocks.saturating_sub(1);
+    }
+    while let Some((address, amount, 0)) = state.get_unbonding().front().copied() {
+        state.get_unbonding_mut().pop_front();
+        let balance = get_balance(state, &address);
+        insert_balance(state, address, balance + amount);
+    }
+    for (_, _, blocks) in state.get_commission_changes_mut().iter_mut() {
+        *blocks = blocks.saturating_sub(1);
+    }
+    while let Some((staker, commission, 0)) = state.get_commission_changes().front().copied() {
+        state.get_commission_changes_mut().pop_front();
+        insert_commission(state, staker, commission);
+    }
 }
-fn update_1<T: State>(state: &mut T, block: &BlockA) {
+fn update_1<T: State>(state: &mut T, spec: &ChainSpec, block: &BlockA) {
     let input_address = block.input_address();
+    let issuance = *state.get_issuance();
+    let minted = spec.emission.reward(issuance.height, issuance.supply);
+    let burned = block.fees() * spec.fee_burn as u128 / 100;
+    let mut reward = block.reward(minted) - burned;
+    let issuance = state.get_issuance_mut();
+    issuance.height += 1;
+    issuance.supply += minted;
+    burn(state, burned);
+    let delegated = get_delegated(state, &input_address);
+    if delegated > 0 {
+        let pool = util::share(reward, delegated, get_staked(state, &input_address) + delegated);
+        let pool = pool - util::share(pool, get_commission(state, &input_address) as u128, 100);
+        for (delegator, amount) in get_delegators(state, &input_address) {
+            let part = util::share(pool, amount, delegated);
+            reward -= part;
+            let balance = get_balance(state, &delegator);
+            insert_balance(state, delegator, balance + part);
+        }
+    }
     let mut balance = get_balance(state, &input_address);
-    balance += block.reward();
+    balance += reward;
     if let Some(stake) = block.stakes.first() {
         if stake.fee == 0 {
+            let staked = get_staked(state, &input_address);
+            let issuance = state.get_issuance_mut();
+            issuance.supply = issuance.supply + COIN - staked;
             insert_staked(state, input_address, COIN)
         }
     }
     insert_balance(state, input_address, balance)
 }
-fn update_2<T: State>(state: &mut T, block: &BlockA) {
+fn update_2<T: State>(state: &mut T, spec: &ChainSpec, block: &BlockA) {
     for transaction in block.transactions.iter() {
         let mut balance_input = get_balance(state, &transaction.input_address);
-        let mut balance_output = get_balance(state, &transaction.output_address);
-        balance_input -= transaction.amount + transaction.fee;
-        balance_output += transaction.amount;
+        balance_input -= transaction.amount() + transaction.fee;
         insert_balance(state, transaction.input_address, balance_input);
-        insert_balance(state, transaction.output_address, balance_output);
+        match &transaction.htlc {
+            Some(Htlc::Lock { hash_lock, timeout }) => {
+                let (recipient, amount) = transaction.outputs[0];
+                state.get_map_locked_mut().insert(
+                    transaction.hash,
+                    Lock {
+                        sender: transaction.input_address,
+                        recipient,
+                        amount,
+                        hash_lock: *hash_lock,
+                        timeout: *timeout,
+                    },
+                );
+            }
+            Some(Htlc::Claim { lock, .. }) | Some(Htlc::Refund { lock }) => {
+                if let Some(lock) = state.get_map_locked_mut().remove(lock) {
+                    let balance = get_balance(state, &transaction.input_address);
+                    insert_balance(state, transaction.input_address, balance + lock.amount);
+                }
+            }
+            None => {
+                for (output_address, amount) in transaction.outputs.iter() {
+                    let mut balance_output = get_balance(state, output_address);
+                    balance_output += amount;
+                    insert_balance(state, *output_address, balance_output);
+                }
+            }
+        }
+        state.get_map_nonce_mut().insert(transaction.input_address, transaction.nonce + 1);
     }
     for stake in block.stakes.iter() {
         let mut balance = get_balance(state, &stake.input_address);
-        let mut staked = get_staked(state, &stake.input_address);
+        let mut staked = match stake.delegate {
+            Some(staker) => get_delegation(state, &stake.input_address, &staker),
+            None => get_staked(state, &stake.input_address),
+        };
         if stake.deposit {
             balance -= stake.amount + stake.fee;
             staked += stake.amount;
-        } else {
+        } else if spec.unbonding_blocks == 0 {
             balance += stake.amount - stake.fee;
             staked -= stake.amount;
+        } else {
+            balance -= stake.fee;
+            staked -= stake.amount;
+            state.get_unbonding_mut().push_back((stake.input_address, stake.amount, spec.unbonding_blocks));
         }
         insert_balance(state, stake.input_address, balance);
-        insert_staked(state, stake.input_address, staked);
+        match stake.delegate {
+            Some(staker) => insert_delegation(state, stake.input_address, staker, staked),
+            None => {
+                insert_staked(state, stake.input_address, staked);
+                if stake.version >= 2 {
+                    update_commission(state, spec, stake.input_address, stake.commission);
+                }
+            }
+        }
     }
 }
 fn update_3<T: State>(state: &mut T, block: &BlockA) {
@@ -242,30 +662,41 @@ fn update_3<T: State>(state: &mut T, block: &BlockA) {
         update_stakers(state, stake.input_address);
     }
 }
-pub fn update<T: State>(state: &mut T, block: &BlockA, previous_timestamp: u32, loading: bool) {
-    update_0(state, block.timestamp, previous_timestamp, loading);
-    update_1(state, block);
-    update_2(state, block);
+pub fn genesis<T: State + Default>(spec: &ChainSpec) -> T {
+    let mut state = T::default();
+    state.get_latest_block_mut().timestamp = spec.genesis.timestamp;
+    for (address, balance) in spec.genesis.balances().unwrap() {
+        state.get_issuance_mut().supply += balance;
+        insert_balance(&mut state, address, balance);
+    }
+    for (address, staked) in spec.genesis.staked().unwrap() {
+        state.get_issuance_mut().supply += staked;
+        insert_staked(&mut state, address, staked);
+        update_stakers(&mut state, address);
+    }
+    state
+}
+pub fn update<T: State>(state: &mut T, spec: &ChainSpec, block: &BlockA, previous_timestamp: u32, loading: bool) {
+    update_0(state, spec, block, previous_timestamp, loading);
+    update_1(state, spec, block);
+    update_2(state, spec, block);
     update_3(state, block);
 }
-pub fn append_block<T: State>(state: &mut T, db: &DBWithThreadMode<SingleThreaded>, block: &BlockA, previous_timestamp: u32, loading: bool) {
+pub fn append_block<T: State>(state: &mut T, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, block: &BlockA, previous_timestamp: u32, loading: bool) {
     state.get_hashes_mut().push(block.hash);
-    update(state, block, previous_timestamp, loading);
+    update(state, spec, block, previous_timestamp, loading);
     *state.get_latest_block_mut() = db::block::get_a(db, &block.hash).unwrap();
 }
-pub fn load<T: State>(state: &mut T, db: &DBWithThreadMode<SingleThreaded>, hashes: &[Hash]) {
-    let mut previous_timestamp = match hashes.first() {
-        Some(hash) => db::block::get_b(db, hash).unwrap().timestamp,
-        None => 0,
-    };
+pub fn load<T: State>(state: &mut T, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, hashes: &[Hash]) {
+    let mut previous_timestamp = state.get_latest_block().timestamp;
     for hash in hashes.iter() {
         let block_a = db::block::get_a(db, hash).unwrap();
-        state.append_block(db, &block_a, previous_timestamp, true);
+        state.append_block(db, spec, &block_a, previous_timestamp, true);
         previous_timestamp = block_a.timestamp;
     }
 }
 fn stakers_n<T: State>(state: &T, n: usize) -> (Vec<AddressBytes>, bool) {
-    fn random_n(vec: &Vec<(AddressBytes, u128)>, beta: &Beta, n: u128, modulo: u128) -> usize {
+    fn random_n(vec: &[(AddressBytes, u128)], beta: &Beta, n: u128, modulo: u128) -> usize {
         let random = util::random(beta, n, modulo);
         let mut counter = 0;
         for (index, (_, staked)) in vec.iter().enumerate() {
@@ -279,11 +710,11 @@ fn stakers_n<T: State>(state: &T, n: usize) -> (Vec<AddressBytes>, bool) {
     let mut modulo = 0;
     let mut vec: Vec<(AddressBytes, u128)> = vec![];
     for staker in state.get_stakers().iter() {
-        let staked = get_staked(state, staker);
+        let staked = get_weight(state, staker);
         modulo += staked;
-        vec.push((staker.clone(), staked));
+        vec.push((*staker, staked));
     }
-    vec.sort_by(|a, b| b.1.cmp(&a.1));
+    vec.sort_by_key(|x| std::cmp::Reverse(x.1));
     let mut random_queue = vec![];
     for index in 0..(n + 1) {
         let penalty = util::penalty(index);
@@ -297,19 +728,170 @@ fn stakers_n<T: State>(state: &T, n: usize) -> (Vec<AddressBytes>, bool) {
     }
     (random_queue, false)
 }
-fn offline(timestamp: u32, previous_timestamp: u32) -> usize {
+pub fn offline(spec: &ChainSpec, timestamp: u32, previous_timestamp: u32) -> usize {
     let diff = timestamp.saturating_sub(previous_timestamp + 1);
-    (diff / BLOCK_TIME_MAX as u32) as usize
+    (diff / spec.block_time_max()) as usize
 }
-pub fn next_staker<T: State>(state: &T, timestamp: u32) -> Option<AddressBytes> {
-    match stakers_n(state, offline(timestamp, state.get_latest_block().timestamp)) {
+pub fn next_staker<T: State>(state: &T, spec: &ChainSpec, timestamp: u32) -> Option<AddressBytes> {
+    match stakers_n(state, offline(spec, timestamp, state.get_latest_block().timestamp)) {
         (_, true) => None,
         (x, _) => x.last().copied(),
     }
 }
-fn stakers_offline<T: State>(state: &T, timestamp: u32, previous_timestamp: u32) -> Vec<AddressBytes> {
-    match offline(timestamp, previous_timestamp) {
+fn stakers_offline<T: State>(state: &T, spec: &ChainSpec, timestamp: u32, previous_timestamp: u32) -> Vec<AddressBytes> {
+    match offline(spec, timestamp, previous_timestamp) {
         0 => vec![],
         n => stakers_n(state, n - 1).0,
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::Emission;
+    use pea_block::Evidence;
+    use pea_key::Key;
+    use pea_stake::StakeA;
+    use pea_transaction::TransactionA;
+    #[test]
+    fn test_unbonding() {
+        let mut spec = ChainSpec::devnet();
+        spec.emission = Emission::Constant { reward: 0 };
+        spec.genesis.balances.insert(address::encode(&[1; 20]), COIN);
+        spec.genesis.staked.insert(address::encode(&[1; 20]), 2 * COIN);
+        let mut dynamic = Dynamic::genesis(&spec);
+        let stake_a = StakeA {
+            amount: COIN,
+            fee: 1,
+            input_address: [1; 20],
+            ..StakeA::default()
+        };
+        let block_a = BlockA {
+            stakes: vec![stake_a],
+            ..BlockA::default()
+        };
+        update(&mut dynamic, &spec, &block_a, 0, true);
+        assert_eq!(dynamic.staked(&[1; 20]), COIN);
+        assert_eq!(dynamic.balance(&[1; 20]), COIN - 1);
+        assert_eq!(dynamic.unbonding(&[1; 20]), vec![(COIN, spec.unbonding_blocks)]);
+        for _ in 0..spec.unbonding_blocks {
+            update(&mut dynamic, &spec, &BlockA::default(), 0, true);
+        }
+        assert_eq!(dynamic.balance(&[1; 20]), 2 * COIN - 1);
+        assert!(dynamic.unbonding(&[1; 20]).is_empty());
+    }
+    #[test]
+    fn test_snapshot() {
+        let tempdir = tempdir::TempDir::new("peacash-db").unwrap();
+        let db = db::open(tempdir.path().to_str().unwrap());
+        let mut spec = ChainSpec::devnet();
+        spec.genesis.balances.insert(address::encode(&[1; 20]), COIN);
+        let mut trusted = Trusted::genesis(&spec);
+        trusted.hashes = vec![[1; 32], [2; 32]];
+        trusted.latest_block.hash = [2; 32];
+        trusted.snapshot(&db, &spec).unwrap();
+        assert!(Trusted::restore(&db, &[[1; 32], [3; 32]]).unwrap().is_none());
+        let restored = Trusted::restore(&db, &[[1; 32], [2; 32], [3; 32]]).unwrap().unwrap();
+        assert_eq!(restored.hashes, trusted.hashes);
+        assert_eq!(get_balance(&restored, &[1; 20]), COIN);
+    }
+    #[test]
+    fn test_issuance() {
+        let mut spec = ChainSpec::devnet();
+        spec.emission = Emission::Constant { reward: 1_000 };
+        spec.fee_burn = 50;
+        spec.genesis.balances.insert(address::encode(&[1; 20]), COIN);
+        let mut dynamic = Dynamic::genesis(&spec);
+        assert_eq!(dynamic.issuance().supply, COIN);
+        let transaction_a = TransactionA {
+            input_address: [1; 20],
+            fee: 100,
+            ..TransactionA::default()
+        };
+        let block_a = BlockA {
+            transactions: vec![transaction_a],
+            ..BlockA::default()
+        };
+        update(&mut dynamic, &spec, &block_a, 0, true);
+        assert_eq!(dynamic.balance(&block_a.input_address()), 1_050);
+        assert_eq!(dynamic.issuance().supply, COIN + 950);
+        assert_eq!(dynamic.issuance().burned, 50);
+        assert_eq!(dynamic.issuance().height, 1);
+    }
+    #[test]
+    fn test_commission_delay() {
+        let spec = ChainSpec::devnet();
+        let mut dynamic = Dynamic::genesis(&spec);
+        let stake_a = StakeA {
+            version: 2,
+            deposit: true,
+            commission: 10,
+            input_address: [1; 20],
+            ..StakeA::default()
+        };
+        let block_a = BlockA {
+            stakes: vec![stake_a.clone()],
+            ..BlockA::default()
+        };
+        update(&mut dynamic, &spec, &block_a, 0, true);
+        assert_eq!(dynamic.commission(&[1; 20]), 0);
+        for _ in 0..spec.unbonding_blocks - 1 {
+            update(&mut dynamic, &spec, &BlockA::default(), 0, true);
+        }
+        assert_eq!(dynamic.commission(&[1; 20]), 0);
+        update(&mut dynamic, &spec, &BlockA::default(), 0, true);
+        assert_eq!(dynamic.commission(&[1; 20]), 10);
+        let block_a = BlockA {
+            stakes: vec![StakeA { commission: 5, ..stake_a }],
+            ..BlockA::default()
+        };
+        update(&mut dynamic, &spec, &block_a, 0, true);
+        assert_eq!(dynamic.commission(&[1; 20]), 5);
+        assert!(dynamic.commission_changes.is_empty());
+    }
+    #[test]
+    fn test_delegation_reward() {
+        let mut spec = ChainSpec::devnet();
+        spec.emission = Emission::Constant { reward: 1_000 };
+        let staker = BlockA::default().input_address();
+        spec.genesis.staked.insert(address::encode(&staker), COIN);
+        let mut dynamic = Dynamic::genesis(&spec);
+        insert_commission(&mut dynamic, staker, 10);
+        insert_delegation(&mut dynamic, [2; 20], staker, COIN);
+        assert_eq!(dynamic.delegated(&staker), COIN);
+        update(&mut dynamic, &spec, &BlockA::default(), 0, true);
+        assert_eq!(dynamic.balance(&[2; 20]), 450);
+        assert_eq!(dynamic.balance(&staker), 550);
+        insert_delegation(&mut dynamic, [2; 20], staker, 0);
+        assert_eq!(dynamic.delegated(&staker), 0);
+        assert!(dynamic.delegators(&staker).is_empty());
+    }
+    #[test]
+    fn test_slash_delegated() {
+        let mut spec = ChainSpec::devnet();
+        spec.emission = Emission::Constant { reward: 0 };
+        let key = Key::generate();
+        let staker = key.address_bytes();
+        spec.genesis.staked.insert(address::encode(&staker), 2 * COIN);
+        spec.genesis.balances.insert(address::encode(&[2; 20]), COIN);
+        let mut dynamic = Dynamic::genesis(&spec);
+        insert_balance(&mut dynamic, [2; 20], 0);
+        insert_delegation(&mut dynamic, [2; 20], staker, COIN);
+        let block_c = |timestamp| {
+            BlockA::sign(spec.chain_id, [0; 32], timestamp, vec![], vec![], vec![], &key, &GENESIS_BETA)
+                .unwrap()
+                .b()
+                .c()
+        };
+        let block_a = BlockA {
+            evidence: vec![Evidence::new(block_c(1), block_c(2))],
+            ..BlockA::default()
+        };
+        let supply = dynamic.issuance().supply;
+        update(&mut dynamic, &spec, &block_a, 0, true);
+        assert_eq!(dynamic.staked(&staker), 0);
+        assert_eq!(dynamic.delegated(&staker), 0);
+        assert!(dynamic.delegators(&staker).is_empty());
+        assert_eq!(dynamic.weight(&staker), 0);
+        assert_eq!(dynamic.issuance().supply, supply - 3 * COIN);
+    }
+}
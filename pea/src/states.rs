@@ -1,9 +1,10 @@
 use crate::{
     blockchain::Blockchain,
+    spec::ChainSpec,
     state::{Dynamic, Trusted},
 };
 use colored::*;
-use log::debug;
+use log::{debug, warn};
 use pea_core::*;
 use pea_db as db;
 use rocksdb::{DBWithThreadMode, SingleThreaded};
@@ -14,15 +15,18 @@ pub struct States {
     pub trusted: Trusted,
 }
 impl States {
-    pub fn new() -> States {
+    pub fn new(spec: &ChainSpec) -> States {
         States {
-            dynamic: Dynamic::default(),
-            trusted: Trusted::default(),
+            dynamic: Dynamic::genesis(spec),
+            trusted: Trusted::genesis(spec),
         }
     }
     pub fn dynamic_fork(&self, blockchain: &Blockchain, previous_hash: &Hash) -> Result<Dynamic, Box<dyn Error>> {
         if previous_hash == &[0; 32] {
-            return Ok(Dynamic::default());
+            if blockchain.finalized.is_some() {
+                return Err("not allowed to fork finalized chain".into());
+            }
+            return Ok(Dynamic::genesis(&blockchain.spec));
         }
         let mut hashes = vec![];
         if let Some(first) = blockchain.states.dynamic.hashes.first() {
@@ -47,28 +51,34 @@ impl States {
             }
             hashes.reverse();
         }
-        Ok(Dynamic::from(&blockchain.db, &hashes, &self.trusted))
+        if let Some(finalized) = &blockchain.finalized {
+            if blockchain.states.dynamic.hashes.contains(finalized) && !hashes.contains(finalized) {
+                return Err("not allowed to fork finalized chain".into());
+            }
+        }
+        Ok(Dynamic::from(&blockchain.db, &blockchain.spec, &hashes, &self.trusted))
     }
-    pub fn update(&mut self, db: &DBWithThreadMode<SingleThreaded>, hashes_1: &[Hash], trust_fork_after_blocks: usize) {
+    pub fn update(&mut self, db: &DBWithThreadMode<SingleThreaded>, spec: &ChainSpec, hashes_1: &[Hash], trust_fork_after_blocks: usize) {
         let start = Instant::now();
         let hashes_0 = &self.dynamic.hashes;
         if hashes_0.len() == trust_fork_after_blocks {
             let block_a = db::block::get_a(db, hashes_0.first().unwrap()).unwrap();
             self.trusted.append_block(
                 db,
+                spec,
                 &block_a,
-                match db::block::get_b(db, &block_a.previous_hash) {
-                    Ok(block_b) => block_b.timestamp,
-                    Err(_) => 0,
+                match db::block::get_c(db, &block_a.previous_hash) {
+                    Ok(block_c) => block_c.timestamp,
+                    Err(_) => spec.genesis.timestamp,
                 },
             );
+            if spec.snapshot_interval != 0 && self.trusted.hashes.len().is_multiple_of(spec.snapshot_interval) {
+                if let Err(err) = self.trusted.snapshot(db, spec) {
+                    warn!("Snapshot {}", err);
+                }
+            }
         }
-        self.dynamic = Dynamic::from(db, hashes_1, &self.trusted);
+        self.dynamic = Dynamic::from(db, spec, hashes_1, &self.trusted);
         debug!("{} {:?}", "States update".cyan(), start.elapsed());
     }
 }
-impl Default for States {
-    fn default() -> Self {
-        Self::new()
-    }
-}
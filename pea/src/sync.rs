@@ -1,16 +1,21 @@
-use lazy_static::lazy_static;
-use pea_core::*;
-lazy_static! {
-    static ref BPS: f32 = 0.5_f32 + (1_f32 / 2_f32.powf(BLOCK_TIME_MIN as f32));
-}
 #[derive(Debug)]
 pub struct Sync {
     pub index: usize,
     pub bps: f32,
     pub new: f32,
     pub completed: bool,
+    threshold: f32,
 }
 impl Sync {
+    pub fn new(block_time_min: u32) -> Self {
+        Self {
+            index: 0,
+            bps: 0.0,
+            new: 0.0,
+            completed: false,
+            threshold: 0.5_f32 + (1_f32 / 2_f32.powf(block_time_min as f32)),
+        }
+    }
     pub fn handler(&mut self) {
         self.bps += self.new;
         self.bps /= 2.0;
@@ -18,16 +23,6 @@ impl Sync {
         self.completed = !self.downloading();
     }
     pub fn downloading(&self) -> bool {
-        self.bps > *BPS
-    }
-}
-impl Default for Sync {
-    fn default() -> Self {
-        Self {
-            index: 0,
-            bps: 0.0,
-            new: 0.0,
-            completed: false,
-        }
+        self.bps > self.threshold
     }
 }
@@ -6,9 +6,13 @@ use std::{
     io::{BufRead, BufReader},
     path::Path,
 };
-construct_uint! {
-    pub struct U256(4);
+#[allow(clippy::manual_div_ceil, clippy::assign_op_pattern)]
+mod uint {
+    construct_uint! {
+        pub struct U256(4);
+    }
 }
+pub use uint::U256;
 pub fn u256(hash: &Hash) -> U256 {
     U256::from_big_endian(hash)
 }
@@ -30,6 +34,10 @@ pub fn penalty(index: usize) -> u128 {
     }
     COIN * 2u128.pow(index as u32 - 1)
 }
+pub fn share(amount: u128, numerator: u128, denominator: u128) -> u128 {
+    let ppb = 1_000_000_000;
+    amount * (numerator * ppb / denominator) / ppb
+}
 pub fn timestamp() -> u32 {
     chrono::offset::Utc::now().timestamp() as u32
 }
@@ -4,52 +4,92 @@ use serde::{Deserialize, Serialize};
 use serde_big_array::BigArray;
 use sha2::{Digest, Sha256};
 use std::error::Error;
+pub const VERSION: u8 = 2;
 pub trait Stake {
+    fn get_version(&self) -> u8;
+    fn get_chain_id(&self) -> u32;
     fn get_timestamp(&self) -> u32;
     fn get_deposit(&self) -> bool;
+    fn get_amount_bytes(&self) -> AmountBytes;
     fn get_fee_bytes(&self) -> AmountBytes;
+    fn get_delegate(&self) -> Option<AddressBytes>;
+    fn get_commission(&self) -> u8;
     fn hash(&self) -> Hash;
-    fn hash_input(&self) -> [u8; 9];
+    fn hash_input(&self) -> Vec<u8>;
 }
 impl Stake for StakeA {
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+    fn get_chain_id(&self) -> u32 {
+        self.chain_id
+    }
     fn get_timestamp(&self) -> u32 {
         self.timestamp
     }
     fn get_deposit(&self) -> bool {
         self.deposit
     }
+    fn get_amount_bytes(&self) -> AmountBytes {
+        pea_int::to_be_bytes(self.amount)
+    }
     fn get_fee_bytes(&self) -> AmountBytes {
         pea_int::to_be_bytes(self.fee)
     }
+    fn get_delegate(&self) -> Option<AddressBytes> {
+        self.delegate
+    }
+    fn get_commission(&self) -> u8 {
+        self.commission
+    }
     fn hash(&self) -> Hash {
         hash(self)
     }
-    fn hash_input(&self) -> [u8; 9] {
+    fn hash_input(&self) -> Vec<u8> {
         hash_input(self)
     }
 }
 impl Stake for StakeB {
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+    fn get_chain_id(&self) -> u32 {
+        self.chain_id
+    }
     fn get_timestamp(&self) -> u32 {
         self.timestamp
     }
     fn get_deposit(&self) -> bool {
         self.deposit
     }
+    fn get_amount_bytes(&self) -> AmountBytes {
+        self.amount
+    }
     fn get_fee_bytes(&self) -> AmountBytes {
         self.fee
     }
+    fn get_delegate(&self) -> Option<AddressBytes> {
+        self.delegate
+    }
+    fn get_commission(&self) -> u8 {
+        self.commission
+    }
     fn hash(&self) -> Hash {
         hash(self)
     }
-    fn hash_input(&self) -> [u8; 9] {
+    fn hash_input(&self) -> Vec<u8> {
         hash_input(self)
     }
 }
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct StakeA {
+    pub version: u8,
+    pub chain_id: u32,
     pub amount: u128,
     pub fee: u128,
     pub deposit: bool,
+    pub delegate: Option<AddressBytes>,
+    pub commission: u8,
     pub timestamp: u32,
     #[serde(with = "BigArray")]
     pub signature: SignatureBytes,
@@ -58,9 +98,13 @@ pub struct StakeA {
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StakeB {
+    pub version: u8,
+    pub chain_id: u32,
     pub amount: AmountBytes,
     pub fee: AmountBytes,
     pub deposit: bool,
+    pub delegate: Option<AddressBytes>,
+    pub commission: u8,
     pub timestamp: u32,
     #[serde(with = "BigArray")]
     pub signature: SignatureBytes,
@@ -68,9 +112,13 @@ pub struct StakeB {
 impl StakeA {
     pub fn b(&self) -> StakeB {
         StakeB {
+            version: self.version,
+            chain_id: self.chain_id,
             amount: pea_int::to_be_bytes(self.amount),
             fee: pea_int::to_be_bytes(self.fee),
             deposit: self.deposit,
+            delegate: self.delegate,
+            commission: self.commission,
             timestamp: self.timestamp,
             signature: self.signature,
         }
@@ -78,20 +126,47 @@ impl StakeA {
     pub fn hash(&self) -> Hash {
         hash(self)
     }
-    pub fn sign(deposit: bool, amount: u128, fee: u128, timestamp: u32, key: &Key) -> Result<StakeA, Box<dyn Error>> {
-        let mut stake_a = StakeA {
+    pub fn sign(chain_id: u32, deposit: bool, amount: u128, fee: u128, timestamp: u32, key: &Key) -> Result<StakeA, Box<dyn Error>> {
+        StakeA::sign_commission(chain_id, deposit, amount, fee, 0, timestamp, key)
+    }
+    pub fn sign_commission(chain_id: u32, deposit: bool, amount: u128, fee: u128, commission: u8, timestamp: u32, key: &Key) -> Result<StakeA, Box<dyn Error>> {
+        StakeA {
+            chain_id,
             amount: pea_int::floor(amount),
             fee: pea_int::floor(fee),
             deposit,
+            commission,
             timestamp,
-            signature: [0; 64],
-            input_address: [0; 20],
-            hash: [0; 32],
-        };
-        stake_a.hash = stake_a.hash();
-        stake_a.signature = key.sign(&stake_a.hash)?;
-        stake_a.input_address = key.address_bytes();
-        Ok(stake_a)
+            ..StakeA::default()
+        }
+        .signed(key)
+    }
+    pub fn sign_delegate(
+        chain_id: u32,
+        deposit: bool,
+        delegate: AddressBytes,
+        amount: u128,
+        fee: u128,
+        timestamp: u32,
+        key: &Key,
+    ) -> Result<StakeA, Box<dyn Error>> {
+        StakeA {
+            chain_id,
+            amount: pea_int::floor(amount),
+            fee: pea_int::floor(fee),
+            deposit,
+            delegate: Some(delegate),
+            timestamp,
+            ..StakeA::default()
+        }
+        .signed(key)
+    }
+    fn signed(mut self, key: &Key) -> Result<StakeA, Box<dyn Error>> {
+        self.version = VERSION;
+        self.hash = self.hash();
+        self.signature = key.sign(&self.hash)?;
+        self.input_address = key.address_bytes();
+        Ok(self)
     }
 }
 impl StakeB {
@@ -101,9 +176,13 @@ impl StakeB {
             None => self.input_address()?,
         };
         Ok(StakeA {
+            version: self.version,
+            chain_id: self.chain_id,
             amount: pea_int::from_be_slice(&self.amount),
             fee: pea_int::from_be_slice(&self.fee),
             deposit: self.deposit,
+            delegate: self.delegate,
+            commission: self.commission,
             timestamp: self.timestamp,
             signature: self.signature,
             input_address,
@@ -117,27 +196,48 @@ impl StakeB {
         Ok(Key::address(&self.input_public_key()?))
     }
     fn input_public_key(&self) -> Result<PublicKeyBytes, Box<dyn Error>> {
-        Ok(Key::recover(&self.hash(), &self.signature)?)
+        Key::recover(&self.hash(), &self.signature)
     }
 }
 fn hash<T: Stake>(stake: &T) -> Hash {
     let mut hasher = Sha256::new();
-    hasher.update(&stake.hash_input());
+    hasher.update(stake.hash_input());
     hasher.finalize().into()
 }
-fn hash_input<T: Stake>(stake: &T) -> [u8; 9] {
-    let mut bytes = [0; 9];
-    bytes[0..4].copy_from_slice(&stake.get_timestamp().to_be_bytes());
-    bytes[4..8].copy_from_slice(&stake.get_fee_bytes());
-    bytes[8] = if stake.get_deposit() { 1 } else { 0 };
-    bytes
+fn hash_input<T: Stake>(stake: &T) -> Vec<u8> {
+    let chain_id = stake.get_chain_id().to_be_bytes();
+    let timestamp = stake.get_timestamp().to_be_bytes();
+    let deposit = if stake.get_deposit() { 1 } else { 0 };
+    let delegate = match stake.get_delegate() {
+        Some(address) => [&[1][..], &address].concat(),
+        None => vec![0],
+    };
+    match stake.get_version() {
+        0 => [&timestamp[..], &stake.get_fee_bytes(), &[deposit]].concat(),
+        1 => [&[1], &chain_id[..], &timestamp, &stake.get_amount_bytes(), &stake.get_fee_bytes(), &[deposit]].concat(),
+        version => [
+            &[version],
+            &chain_id[..],
+            &timestamp,
+            &stake.get_amount_bytes(),
+            &stake.get_fee_bytes(),
+            &[deposit],
+            &delegate,
+            &[stake.get_commission()],
+        ]
+        .concat(),
+    }
 }
 impl Default for StakeA {
     fn default() -> Self {
         StakeA {
+            version: 0,
+            chain_id: 0,
             amount: 0,
             fee: 0,
             deposit: false,
+            delegate: None,
+            commission: 0,
             timestamp: 0,
             signature: [0; 64],
             input_address: [0; 20],
@@ -148,9 +248,13 @@ impl Default for StakeA {
 impl Default for StakeB {
     fn default() -> Self {
         StakeB {
+            version: 0,
+            chain_id: 0,
             amount: [0; AMOUNT_BYTES],
             fee: [0; AMOUNT_BYTES],
             deposit: false,
+            delegate: None,
+            commission: 0,
             timestamp: 0,
             signature: [0; 64],
         }
@@ -168,5 +272,52 @@ mod tests {
                 92, 125
             ]
         );
+        assert_eq!(
+            StakeB {
+                version: 1,
+                ..StakeB::default()
+            }
+            .hash(),
+            [
+                170, 37, 225, 213, 149, 167, 124, 55, 91, 64, 180, 121, 110, 164, 255, 4, 72, 144, 239, 238, 189, 75, 209, 93, 22, 213, 67, 135, 165, 168, 252,
+                55
+            ]
+        );
+    }
+    #[test]
+    fn test_hash_input_amount() {
+        let amount = pea_int::to_be_bytes(COIN);
+        assert_eq!(StakeB { amount, ..StakeB::default() }.hash(), StakeB::default().hash());
+        let stake_b = StakeB {
+            version: 1,
+            ..StakeB::default()
+        };
+        assert_ne!(StakeB { amount, ..stake_b.clone() }.hash(), stake_b.hash());
+    }
+    #[test]
+    fn test_hash_input_delegate() {
+        let stake_b = StakeB {
+            delegate: Some([0; 20]),
+            commission: 10,
+            ..StakeB::default()
+        };
+        assert_eq!(stake_b.hash(), StakeB::default().hash());
+        let stake_b = StakeB { version: 2, ..stake_b };
+        assert_ne!(
+            StakeB {
+                delegate: None,
+                ..stake_b.clone()
+            }
+            .hash(),
+            stake_b.hash()
+        );
+        assert_ne!(
+            StakeB {
+                commission: 0,
+                ..stake_b.clone()
+            }
+            .hash(),
+            stake_b.hash()
+        );
     }
 }
@@ -5,59 +5,126 @@ use serde_big_array::BigArray;
 use sha2::{Digest, Sha256};
 use std::error::Error;
 pub trait Transaction {
-    fn get_output_address(&self) -> &AddressBytes;
+    fn get_chain_id(&self) -> u32;
+    fn get_nonce(&self) -> u64;
+    fn get_outputs_bytes(&self) -> Vec<(AddressBytes, AmountBytes)>;
     fn get_timestamp(&self) -> u32;
-    fn get_amount_bytes(&self) -> AmountBytes;
     fn get_fee_bytes(&self) -> AmountBytes;
+    fn get_memo(&self) -> &[u8];
+    fn get_multisig(&self) -> &Option<Multisig>;
+    fn get_htlc(&self) -> &Option<Htlc>;
     fn hash(&self) -> Hash;
-    fn hash_input(&self) -> [u8; 32];
+    fn hash_input(&self) -> Vec<u8>;
 }
 impl Transaction for TransactionA {
-    fn get_output_address(&self) -> &AddressBytes {
-        &self.output_address
+    fn get_chain_id(&self) -> u32 {
+        self.chain_id
+    }
+    fn get_nonce(&self) -> u64 {
+        self.nonce
+    }
+    fn get_outputs_bytes(&self) -> Vec<(AddressBytes, AmountBytes)> {
+        self.outputs.iter().map(|(address, amount)| (*address, pea_int::to_be_bytes(*amount))).collect()
     }
     fn get_timestamp(&self) -> u32 {
         self.timestamp
     }
-    fn get_amount_bytes(&self) -> AmountBytes {
-        pea_int::to_be_bytes(self.amount)
-    }
     fn get_fee_bytes(&self) -> AmountBytes {
         pea_int::to_be_bytes(self.fee)
     }
+    fn get_memo(&self) -> &[u8] {
+        &self.memo
+    }
+    fn get_multisig(&self) -> &Option<Multisig> {
+        &self.multisig
+    }
+    fn get_htlc(&self) -> &Option<Htlc> {
+        &self.htlc
+    }
     fn hash(&self) -> Hash {
         hash(self)
     }
-    fn hash_input(&self) -> [u8; 32] {
+    fn hash_input(&self) -> Vec<u8> {
         hash_input(self)
     }
 }
 impl Transaction for TransactionB {
-    fn get_output_address(&self) -> &AddressBytes {
-        &self.output_address
+    fn get_chain_id(&self) -> u32 {
+        self.chain_id
+    }
+    fn get_nonce(&self) -> u64 {
+        self.nonce
+    }
+    fn get_outputs_bytes(&self) -> Vec<(AddressBytes, AmountBytes)> {
+        self.outputs.clone()
     }
     fn get_timestamp(&self) -> u32 {
         self.timestamp
     }
-    fn get_amount_bytes(&self) -> AmountBytes {
-        self.amount
-    }
     fn get_fee_bytes(&self) -> AmountBytes {
         self.fee
     }
+    fn get_memo(&self) -> &[u8] {
+        &self.memo
+    }
+    fn get_multisig(&self) -> &Option<Multisig> {
+        &self.multisig
+    }
+    fn get_htlc(&self) -> &Option<Htlc> {
+        &self.htlc
+    }
     fn hash(&self) -> Hash {
         hash(self)
     }
-    fn hash_input(&self) -> [u8; 32] {
+    fn hash_input(&self) -> Vec<u8> {
         hash_input(self)
     }
 }
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Signature(#[serde(with = "BigArray")] pub SignatureBytes);
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Multisig {
+    pub threshold: u8,
+    pub addresses: Vec<AddressBytes>,
+    pub signatures: Vec<Signature>,
+}
+impl Multisig {
+    pub fn address(&self) -> AddressBytes {
+        Key::multisig_address(self.threshold, &self.addresses)
+    }
+    pub fn verify(&self, hash: &Hash) -> Result<(), Box<dyn Error>> {
+        Key::multisig_verify(hash, self.threshold, &self.addresses, &self.signatures.iter().map(|x| x.0).collect::<Vec<_>>())
+    }
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Htlc {
+    Lock { hash_lock: Hash, timeout: u32 },
+    Claim { lock: Hash, preimage: Vec<u8> },
+    Refund { lock: Hash },
+}
+impl Htlc {
+    pub fn lock(&self) -> Option<&Hash> {
+        match self {
+            Htlc::Lock { .. } => None,
+            Htlc::Claim { lock, .. } | Htlc::Refund { lock } => Some(lock),
+        }
+    }
+    pub fn hash_lock(preimage: &[u8]) -> Hash {
+        let mut hasher = Sha256::new();
+        hasher.update(preimage);
+        hasher.finalize().into()
+    }
+}
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TransactionA {
+    pub chain_id: u32,
+    pub nonce: u64,
     pub input_address: AddressBytes,
-    pub output_address: AddressBytes,
-    pub amount: u128,
+    pub outputs: Vec<(AddressBytes, u128)>,
     pub fee: u128,
+    pub memo: Vec<u8>,
+    pub multisig: Option<Multisig>,
+    pub htlc: Option<Htlc>,
     pub timestamp: u32,
     pub hash: Hash,
     #[serde(with = "BigArray")]
@@ -65,9 +132,13 @@ pub struct TransactionA {
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TransactionB {
-    pub output_address: AddressBytes,
-    pub amount: AmountBytes,
+    pub chain_id: u32,
+    pub nonce: u64,
+    pub outputs: Vec<(AddressBytes, AmountBytes)>,
     pub fee: AmountBytes,
+    pub memo: Vec<u8>,
+    pub multisig: Option<Multisig>,
+    pub htlc: Option<Htlc>,
     pub timestamp: u32,
     #[serde(with = "BigArray")]
     pub signature: SignatureBytes,
@@ -75,9 +146,13 @@ pub struct TransactionB {
 impl TransactionA {
     pub fn b(&self) -> TransactionB {
         TransactionB {
-            output_address: self.output_address,
-            amount: pea_int::to_be_bytes(self.amount),
+            chain_id: self.chain_id,
+            nonce: self.nonce,
+            outputs: self.get_outputs_bytes(),
             fee: pea_int::to_be_bytes(self.fee),
+            memo: self.memo.clone(),
+            multisig: self.multisig.clone(),
+            htlc: self.htlc.clone(),
             timestamp: self.timestamp,
             signature: self.signature,
         }
@@ -85,20 +160,96 @@ impl TransactionA {
     pub fn hash(&self) -> Hash {
         hash(self)
     }
-    pub fn sign(public_key_output: AddressBytes, amount: u128, fee: u128, timestamp: u32, key: &Key) -> Result<TransactionA, Box<dyn Error>> {
-        let mut transaction_a = TransactionA {
-            input_address: [0; 20],
-            output_address: public_key_output,
-            amount: pea_int::floor(amount),
+    pub fn sign(
+        chain_id: u32,
+        nonce: u64,
+        output_address: AddressBytes,
+        amount: u128,
+        fee: u128,
+        timestamp: u32,
+        key: &Key,
+    ) -> Result<TransactionA, Box<dyn Error>> {
+        TransactionA::sign_batch(chain_id, nonce, vec![(output_address, amount)], fee, vec![], timestamp, key)
+    }
+    pub fn sign_batch(
+        chain_id: u32,
+        nonce: u64,
+        outputs: Vec<(AddressBytes, u128)>,
+        fee: u128,
+        memo: Vec<u8>,
+        timestamp: u32,
+        key: &Key,
+    ) -> Result<TransactionA, Box<dyn Error>> {
+        TransactionA {
+            chain_id,
+            nonce,
+            outputs: outputs.into_iter().map(|(address, amount)| (address, pea_int::floor(amount))).collect(),
             fee: pea_int::floor(fee),
+            memo,
             timestamp,
-            hash: [0; 32],
-            signature: [0; 64],
-        };
-        transaction_a.hash = transaction_a.hash();
-        transaction_a.signature = key.sign(&transaction_a.hash)?;
-        transaction_a.input_address = key.address_bytes();
-        Ok(transaction_a)
+            ..TransactionA::default()
+        }
+        .signed(key)
+    }
+    pub fn sign_htlc(
+        chain_id: u32,
+        nonce: u64,
+        outputs: Vec<(AddressBytes, u128)>,
+        fee: u128,
+        htlc: Htlc,
+        timestamp: u32,
+        key: &Key,
+    ) -> Result<TransactionA, Box<dyn Error>> {
+        TransactionA {
+            chain_id,
+            nonce,
+            outputs: outputs.into_iter().map(|(address, amount)| (address, pea_int::floor(amount))).collect(),
+            fee: pea_int::floor(fee),
+            htlc: Some(htlc),
+            timestamp,
+            ..TransactionA::default()
+        }
+        .signed(key)
+    }
+    fn signed(mut self, key: &Key) -> Result<TransactionA, Box<dyn Error>> {
+        self.hash = self.hash();
+        self.signature = key.sign(&self.hash)?;
+        self.input_address = key.address_bytes();
+        Ok(self)
+    }
+    pub fn multisig_sign(&mut self, key: &Key) -> Result<(), Box<dyn Error>> {
+        let hash = self.hash();
+        let multisig = self.multisig.as_mut().ok_or("transaction not multisig")?;
+        let address = key.address_bytes();
+        if !multisig.addresses.contains(&address) {
+            return Err("key not in multisig policy".into());
+        }
+        if !multisig
+            .signatures
+            .iter()
+            .any(|x| Key::recover(&hash, &x.0).map(|y| Key::address(&y)).ok() == Some(address))
+        {
+            multisig.signatures.push(Signature(key.sign(&hash)?));
+        }
+        self.input_address = multisig.address();
+        self.hash = hash;
+        Ok(())
+    }
+    pub fn multisig_combine(&mut self, transaction_a: &TransactionA) -> Result<(), Box<dyn Error>> {
+        if self.hash() != transaction_a.hash() {
+            return Err("transaction hash mismatch".into());
+        }
+        let signatures = &transaction_a.multisig.as_ref().ok_or("transaction not multisig")?.signatures;
+        let multisig = self.multisig.as_mut().ok_or("transaction not multisig")?;
+        for signature in signatures {
+            if !multisig.signatures.contains(signature) {
+                multisig.signatures.push(*signature);
+            }
+        }
+        Ok(())
+    }
+    pub fn amount(&self) -> u128 {
+        self.outputs.iter().map(|(_, amount)| amount).sum()
     }
 }
 impl TransactionB {
@@ -108,9 +259,17 @@ impl TransactionB {
             None => self.input_address()?,
         };
         Ok(TransactionA {
-            output_address: self.output_address,
-            amount: pea_int::from_be_slice(&self.amount),
+            chain_id: self.chain_id,
+            nonce: self.nonce,
+            outputs: self
+                .outputs
+                .iter()
+                .map(|(address, amount)| (*address, pea_int::from_be_slice(amount)))
+                .collect(),
             fee: pea_int::from_be_slice(&self.fee),
+            memo: self.memo.clone(),
+            multisig: self.multisig.clone(),
+            htlc: self.htlc.clone(),
             timestamp: self.timestamp,
             signature: self.signature,
             input_address,
@@ -121,31 +280,77 @@ impl TransactionB {
         hash(self)
     }
     fn input_address(&self) -> Result<AddressBytes, Box<dyn Error>> {
-        Ok(Key::address(&self.input_public_key()?))
+        match &self.multisig {
+            Some(multisig) => {
+                multisig.verify(&self.hash())?;
+                Ok(multisig.address())
+            }
+            None => Ok(Key::address(&self.input_public_key()?)),
+        }
     }
     fn input_public_key(&self) -> Result<PublicKeyBytes, Box<dyn Error>> {
-        Ok(Key::recover(&self.hash(), &self.signature)?)
+        Key::recover(&self.hash(), &self.signature)
     }
 }
 fn hash<T: Transaction>(transaction: &T) -> Hash {
     let mut hasher = Sha256::new();
-    hasher.update(&transaction.hash_input());
+    hasher.update(transaction.hash_input());
     hasher.finalize().into()
 }
-fn hash_input<T: Transaction>(transaction: &T) -> [u8; 32] {
-    let mut bytes = [0; 32];
-    bytes[0..20].copy_from_slice(transaction.get_output_address());
-    bytes[20..24].copy_from_slice(&transaction.get_timestamp().to_be_bytes());
-    bytes[24..28].copy_from_slice(&transaction.get_amount_bytes());
-    bytes[28..32].copy_from_slice(&transaction.get_fee_bytes());
+fn hash_input<T: Transaction>(transaction: &T) -> Vec<u8> {
+    let mut bytes = [
+        &transaction.get_chain_id().to_be_bytes()[..],
+        &transaction.get_nonce().to_be_bytes(),
+        &transaction.get_timestamp().to_be_bytes(),
+        &transaction.get_fee_bytes(),
+        &[transaction.get_memo().len() as u8],
+        transaction.get_memo(),
+    ]
+    .concat();
+    match transaction.get_multisig() {
+        Some(multisig) => {
+            bytes.push(multisig.threshold);
+            bytes.push(multisig.addresses.len() as u8);
+            for address in multisig.addresses.iter() {
+                bytes.extend_from_slice(address);
+            }
+        }
+        None => bytes.extend_from_slice(&[0, 0]),
+    }
+    match transaction.get_htlc() {
+        Some(Htlc::Lock { hash_lock, timeout }) => {
+            bytes.push(1);
+            bytes.extend_from_slice(hash_lock);
+            bytes.extend_from_slice(&timeout.to_be_bytes());
+        }
+        Some(Htlc::Claim { lock, preimage }) => {
+            bytes.push(2);
+            bytes.extend_from_slice(lock);
+            bytes.push(preimage.len() as u8);
+            bytes.extend_from_slice(preimage);
+        }
+        Some(Htlc::Refund { lock }) => {
+            bytes.push(3);
+            bytes.extend_from_slice(lock);
+        }
+        None => bytes.push(0),
+    }
+    for (address, amount) in transaction.get_outputs_bytes() {
+        bytes.extend_from_slice(&address);
+        bytes.extend_from_slice(&amount);
+    }
     bytes
 }
 impl Default for TransactionA {
     fn default() -> Self {
         TransactionA {
-            output_address: [0; 20],
-            amount: 0,
+            chain_id: 0,
+            nonce: 0,
+            outputs: vec![],
             fee: 0,
+            memo: vec![],
+            multisig: None,
+            htlc: None,
             timestamp: 0,
             signature: [0; 64],
             input_address: [0; 20],
@@ -156,9 +361,13 @@ impl Default for TransactionA {
 impl Default for TransactionB {
     fn default() -> Self {
         TransactionB {
-            output_address: [0; 20],
-            amount: [0; AMOUNT_BYTES],
+            chain_id: 0,
+            nonce: 0,
+            outputs: vec![],
             fee: [0; AMOUNT_BYTES],
+            memo: vec![],
+            multisig: None,
+            htlc: None,
             timestamp: 0,
             signature: [0; 64],
         }
@@ -172,9 +381,72 @@ mod tests {
         assert_eq!(
             TransactionB::default().hash(),
             [
-                102, 104, 122, 173, 248, 98, 189, 119, 108, 143, 193, 139, 142, 159, 142, 32, 8, 151, 20, 133, 110, 226, 51, 179, 144, 42, 89, 29, 13, 95, 41,
-                37
+                157, 144, 142, 207, 182, 178, 86, 222, 248, 180, 154, 124, 80, 78, 108, 136, 156, 75, 14, 65, 254, 108, 227, 224, 24, 99, 221, 123, 97, 162,
+                10, 160
             ]
         );
     }
+    #[test]
+    fn test_outputs() {
+        let transaction_b = TransactionB {
+            outputs: vec![([0; 20], [0; AMOUNT_BYTES])],
+            ..TransactionB::default()
+        };
+        assert_ne!(transaction_b.hash(), TransactionB::default().hash());
+        let transaction_a = TransactionA {
+            outputs: vec![([0; 20], pea_int::floor(COIN)), ([1; 20], pea_int::floor(COIN))],
+            ..TransactionA::default()
+        };
+        assert_eq!(transaction_a.amount(), 2 * pea_int::floor(COIN));
+        assert_eq!(transaction_a.b().a(Some([0; 20])).unwrap().outputs, transaction_a.outputs);
+    }
+    #[test]
+    fn test_memo() {
+        let transaction_b = TransactionB {
+            memo: b"reference".to_vec(),
+            ..TransactionB::default()
+        };
+        assert_ne!(transaction_b.hash(), TransactionB::default().hash());
+    }
+    #[test]
+    fn test_multisig() {
+        let keys = [Key::generate(), Key::generate(), Key::generate()];
+        let mut addresses = keys.iter().map(|x| x.address_bytes()).collect::<Vec<_>>();
+        addresses.sort();
+        let mut transaction_a = TransactionA {
+            multisig: Some(Multisig {
+                threshold: 2,
+                addresses,
+                signatures: vec![],
+            }),
+            ..TransactionA::default()
+        };
+        let mut other = transaction_a.clone();
+        transaction_a.multisig_sign(&keys[0]).unwrap();
+        assert!(transaction_a.b().a(None).is_err());
+        other.multisig_sign(&keys[1]).unwrap();
+        transaction_a.multisig_combine(&other).unwrap();
+        assert_eq!(transaction_a.b().a(None).unwrap().input_address, transaction_a.input_address);
+        assert!(transaction_a.multisig_sign(&Key::generate()).is_err());
+    }
+    #[test]
+    fn test_htlc() {
+        let transaction_b = TransactionB {
+            htlc: Some(Htlc::Refund { lock: [0; 32] }),
+            ..TransactionB::default()
+        };
+        assert_ne!(transaction_b.hash(), TransactionB::default().hash());
+        assert_ne!(
+            transaction_b.hash(),
+            TransactionB {
+                htlc: Some(Htlc::Claim {
+                    lock: [0; 32],
+                    preimage: vec![]
+                }),
+                ..TransactionB::default()
+            }
+            .hash()
+        );
+        assert_eq!(transaction_b.htlc.unwrap().lock(), Some(&[0; 32]));
+    }
 }
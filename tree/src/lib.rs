@@ -6,12 +6,14 @@ type Branch = (Hash, usize, u32);
 pub struct Tree {
     branches: Vec<Branch>,
     hashes: HashMap<Hash, Hash>,
+    children: HashMap<Hash, Vec<Hash>>,
 }
 impl Tree {
     pub fn new() -> Tree {
         Tree {
             branches: vec![],
             hashes: HashMap::new(),
+            children: HashMap::new(),
         }
     }
     pub fn main(&self) -> Option<&Branch> {
@@ -40,7 +42,7 @@ impl Tree {
         }
         trusted.reverse();
         let len = trusted.len();
-        let start = if len < trust_fork_after_blocks { 0 } else { len - trust_fork_after_blocks };
+        let start = len.saturating_sub(trust_fork_after_blocks);
         let dynamic = trusted.drain(start..len).collect();
         (trusted, dynamic)
     }
@@ -67,10 +69,14 @@ impl Tree {
     pub fn get(&self, hash: &Hash) -> Option<&Hash> {
         self.hashes.get(hash)
     }
+    pub fn children(&self, previous_hash: &Hash) -> Vec<Hash> {
+        self.children.get(previous_hash).cloned().unwrap_or_default()
+    }
     pub fn insert(&mut self, hash: Hash, previous_hash: Hash, timestamp: u32) -> Option<bool> {
         if self.hashes.insert(hash, previous_hash).is_some() {
             return None;
         }
+        self.children.entry(previous_hash).or_default().push(hash);
         if let Some(index) = self.branches.iter().position(|(hash, _, _)| hash == &previous_hash) {
             // extend branch
             self.branches[index] = (hash, self.branches[index].1 + 1, timestamp);
@@ -109,6 +115,7 @@ impl Tree {
     pub fn clear(&mut self) {
         self.branches.clear();
         self.hashes.clear();
+        self.children.clear();
     }
 }
 impl Default for Tree {
@@ -134,5 +141,7 @@ mod tests {
         tree.sort_branches();
         assert_eq!(tree.main(), Some(&([0x77; 32], 3, 0)));
         assert_eq!(tree.size(), 7);
+        assert_eq!(tree.children(&[0x22; 32]), vec![[0x33; 32], [0x55; 32]]);
+        assert_eq!(tree.children(&[0x77; 32]), Vec::<Hash>::new());
     }
 }
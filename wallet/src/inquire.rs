@@ -1,7 +1,7 @@
-use crate::util::{encrypt, filenames, save};
+use crate::util::{encrypt, filenames, multisig_decode, save};
 use colored::*;
-use inquire::{validator::Validation, Confirm, CustomType, Password, PasswordDisplayMode, Select};
-use pea_address::address;
+use inquire::{validator::Validation, Confirm, CustomType, Password, PasswordDisplayMode, Select, Text};
+use pea_address::{address, multisig};
 use pea_core::*;
 use pea_key::Key;
 use std::{error::Error, path::PathBuf, process};
@@ -169,6 +169,158 @@ pub fn address() -> String {
             process::exit(0)
         })
 }
+pub fn threshold() -> u8 {
+    CustomType::<u8>::new("Threshold:")
+        .with_error_message("Please type a valid number")
+        .with_help_message("Type the number of signatures required to spend")
+        .prompt()
+        .unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        })
+}
+pub fn addresses() -> Vec<AddressBytes> {
+    Text::new("Addresses:")
+        .with_help_message("Type the addresses of the signers separated by commas")
+        .with_validator(|input: &str| {
+            if input.split(',').all(|x| address::decode(x.trim()).is_ok()) {
+                Ok(Validation::Valid)
+            } else {
+                Ok(Validation::Invalid("Please enter valid addresses.".into()))
+            }
+        })
+        .prompt()
+        .unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        })
+        .split(',')
+        .map(|x| address::decode(x.trim()).unwrap())
+        .collect()
+}
+pub fn policy() -> (u8, Vec<AddressBytes>) {
+    let policy = Text::new("Policy:")
+        .with_help_message("Type the multisig policy (MULTISIGx...)")
+        .with_validator(|input: &str| match multisig::decode(input) {
+            Ok(_) => Ok(Validation::Valid),
+            Err(_) => Ok(Validation::Invalid("Invalid multisig policy.".into())),
+        })
+        .prompt()
+        .unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        });
+    multisig::decode(&policy).unwrap()
+}
+pub fn multisig_transaction() -> String {
+    Text::new("Transaction:")
+        .with_help_message("Paste the hex encoded multisig transaction")
+        .with_validator(|input: &str| match multisig_decode(input) {
+            Ok(_) => Ok(Validation::Valid),
+            Err(_) => Ok(Validation::Invalid("Invalid multisig transaction.".into())),
+        })
+        .prompt()
+        .unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        })
+}
+pub fn another() -> bool {
+    match Confirm::new("Add another?").prompt() {
+        Ok(b) => b,
+        Err(err) => {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        }
+    }
+}
+pub fn more() -> bool {
+    match Confirm::new("Show more?").prompt() {
+        Ok(b) => b,
+        Err(err) => {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        }
+    }
+}
+pub fn lock() -> Hash {
+    let lock = Text::new("Lock:")
+        .with_help_message("Type the hash of the lock transaction")
+        .with_validator(|input: &str| match hex::decode(input) {
+            Ok(x) if x.len() == 32 => Ok(Validation::Valid),
+            _ => Ok(Validation::Invalid("Please enter a valid hash.".into())),
+        })
+        .prompt()
+        .unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        });
+    hex::decode(lock).unwrap().try_into().unwrap()
+}
+pub fn hash_lock() -> Option<Hash> {
+    let hash_lock = Text::new("Hash lock:")
+        .with_help_message("Type the hash lock of the counterparty, leave empty to generate a new secret")
+        .with_validator(|input: &str| match hex::decode(input) {
+            Ok(x) if x.is_empty() || x.len() == 32 => Ok(Validation::Valid),
+            _ => Ok(Validation::Invalid("Please enter a valid hash.".into())),
+        })
+        .prompt()
+        .unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        });
+    hex::decode(hash_lock).unwrap().try_into().ok()
+}
+pub fn preimage() -> Vec<u8> {
+    let preimage = Text::new("Secret:")
+        .with_help_message("Type the hex encoded secret revealed by the hash lock")
+        .with_validator(|input: &str| match hex::decode(input) {
+            Ok(x) if x.len() <= 32 => Ok(Validation::Valid),
+            _ => Ok(Validation::Invalid("Please enter a valid secret.".into())),
+        })
+        .prompt()
+        .unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        });
+    hex::decode(preimage).unwrap()
+}
+pub fn timeout() -> u32 {
+    CustomType::<u32>::new("Timeout:")
+        .with_formatter(&|i| format!("{} {}", i, if i == 1 { "hour" } else { "hours" }))
+        .with_error_message("Please type a valid number")
+        .with_help_message("Type the number of hours after which the sender can refund")
+        .prompt()
+        .unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        })
+}
+pub fn commission() -> u8 {
+    CustomType::<u8>::new("Commission:")
+        .with_formatter(&|i| format!("{}%", i))
+        .with_error_message("Please type a number between 0 and 100")
+        .with_help_message("Type the percentage of the delegators' reward share kept by the staker")
+        .with_parser(&|x| match x.parse::<u8>() {
+            Ok(u) if u <= 100 => Ok(u),
+            _ => Err(()),
+        })
+        .prompt()
+        .unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        })
+}
+pub fn path() -> String {
+    CustomType::<String>::new("Path:")
+        .with_error_message("Please enter a valid path")
+        .with_help_message("Type the path to a file with one address,amount pair per line")
+        .prompt()
+        .unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        })
+}
 pub fn amount() -> u128 {
     (CustomType::<f64>::new("Amount:")
         .with_formatter(&|i| format!("{:.18} pea", i))
@@ -200,6 +352,23 @@ pub fn fee() -> u128 {
             process::exit(0)
         })
 }
+pub fn memo() -> Vec<u8> {
+    Text::new("Memo:")
+        .with_help_message("Optional reference attached to the transaction, up to 64 bytes")
+        .with_validator(|input: &str| {
+            if input.len() > 64 {
+                Ok(Validation::Invalid("A memo can't be longer than 64 bytes.".into()))
+            } else {
+                Ok(Validation::Valid)
+            }
+        })
+        .prompt()
+        .unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        })
+        .into_bytes()
+}
 pub fn deposit() -> bool {
     match Select::new(">>", vec!["deposit", "withdraw"]).prompt().unwrap_or_else(|err| {
         println!("{}", err.to_string().red());
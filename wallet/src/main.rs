@@ -1,7 +1,7 @@
 use clap::Parser;
 use colored::*;
 use pea_wallet::{
-    util::{clear, press_any_key_to_continue},
+    util::{self, clear, press_any_key_to_continue},
     wallet::{Options, Wallet},
 };
 use std::error::Error;
@@ -11,6 +11,9 @@ pub struct Args {
     /// API Endpoint
     #[clap(long, value_parser, default_value = "localhost:9332")]
     pub api: String,
+    /// Chain preset (mainnet, testnet, devnet) or chain id to sign for
+    #[clap(long, value_parser, default_value = "mainnet")]
+    pub chain: String,
 }
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -21,7 +24,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
     );
     println!("{}/tree/{}", env!("CARGO_PKG_REPOSITORY").yellow(), env!("GIT_HASH").magenta());
     let args = Args::parse();
-    let mut wallet = Wallet::new(Options { api: args.api });
+    let mut wallet = Wallet::new(Options {
+        api: args.api,
+        chain_id: util::chain_id(&args.chain)?,
+    });
     loop {
         if wallet.select().await {
             press_any_key_to_continue();
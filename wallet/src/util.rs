@@ -5,8 +5,10 @@ use chacha20poly1305::{
 };
 use colored::*;
 use crossterm::{event, terminal};
+use pea_address::address;
 use pea_core::*;
 use pea_key::Key;
+use pea_transaction::{TransactionA, TransactionB};
 use std::{
     error::Error,
     fs::{create_dir_all, read_dir, File},
@@ -14,6 +16,14 @@ use std::{
     path::Path,
     process,
 };
+pub fn chain_id(chain: &str) -> Result<u32, Box<dyn Error>> {
+    match chain {
+        "mainnet" => Ok(1),
+        "testnet" => Ok(2),
+        "devnet" => Ok(3),
+        _ => Ok(chain.parse().map_err(|_| "chain preset or id")?),
+    }
+}
 pub fn timestamp() -> u32 {
     chrono::offset::Utc::now().timestamp() as u32
 }
@@ -38,12 +48,8 @@ pub fn encrypt(key: &Key) -> Result<(Salt, Nonce, Ciphertext), Box<dyn Error>> {
     let cipher_key = argon2_key_derivation(passphrase.as_bytes(), &salt);
     let cipher = ChaCha20Poly1305::new_from_slice(&cipher_key)?;
     let nonce: Nonce = rand::random();
-    let ciphertext: Ciphertext = cipher
-        .encrypt(&nonce.try_into()?, key.secret_key_bytes().as_slice())
-        .unwrap()
-        .try_into()
-        .unwrap();
-    Ok((salt, nonce.into(), ciphertext))
+    let ciphertext: Ciphertext = cipher.encrypt(&nonce.into(), key.secret_key_bytes().as_slice()).unwrap().try_into().unwrap();
+    Ok((salt, nonce, ciphertext))
 }
 pub fn decrypt(salt: &Salt, nonce: &Nonce, ciphertext: &Ciphertext, passphrase: &str) -> Result<Vec<u8>, Box<dyn Error>> {
     let passphrase = match passphrase {
@@ -140,6 +146,24 @@ pub fn filenames() -> Result<Vec<String>, Box<dyn Error>> {
     }
     Ok(filenames)
 }
+pub fn outputs(path: impl AsRef<Path>) -> Result<Vec<(AddressBytes, u128)>, Box<dyn Error>> {
+    let mut string = String::new();
+    File::open(path)?.read_to_string(&mut string)?;
+    let mut outputs = vec![];
+    for line in string.lines().filter(|x| !x.trim().is_empty()) {
+        let (address, amount) = line.split_once(',').ok_or("invalid output line")?;
+        outputs.push((address::decode(address.trim())?, pea_int::floor(pea_int::from_str(amount.trim())?)));
+    }
+    Ok(outputs)
+}
+pub fn multisig_encode(transaction_a: &TransactionA) -> Result<String, Box<dyn Error>> {
+    Ok(hex::encode(bincode::serialize(&transaction_a.b())?))
+}
+pub fn multisig_decode(str: &str) -> Result<TransactionA, Box<dyn Error>> {
+    let transaction_b: TransactionB = bincode::deserialize(&hex::decode(str.trim())?)?;
+    let input_address = transaction_b.multisig.as_ref().ok_or("transaction not multisig")?.address();
+    transaction_b.a(Some(input_address))
+}
 pub fn press_any_key_to_continue() {
     println!("{}", "Press any key to continue...".magenta().italic());
     terminal::enable_raw_mode().unwrap();
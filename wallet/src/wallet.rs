@@ -1,17 +1,22 @@
 use crate::{
-    inquire::{address, amount, deposit, fee, search, send},
+    inquire::{
+        address, addresses, amount, another, commission, deposit, fee, hash_lock, lock, memo, more, multisig_transaction, path, policy, preimage, search, send,
+        threshold, timeout,
+    },
     util::{self, Ciphertext, Nonce, Salt},
 };
 use colored::*;
 use inquire::{Confirm, Select};
-use pea_address::{address, secret};
+use pea_address::{address, multisig, secret};
 use pea_api::{get, post};
+use pea_core::*;
 use pea_key::Key;
 use pea_stake::StakeA;
-use pea_transaction::TransactionA;
+use pea_transaction::{Htlc, Multisig, TransactionA};
 use std::process;
 pub struct Options {
     pub api: String,
+    pub chain_id: u32,
 }
 pub struct Wallet {
     key: Option<Key>,
@@ -19,6 +24,7 @@ pub struct Wallet {
     nonce: Nonce,
     ciphertext: Ciphertext,
     api: String,
+    chain_id: u32,
 }
 impl Wallet {
     pub fn new(options: Options) -> Wallet {
@@ -28,12 +34,15 @@ impl Wallet {
             nonce: [0; 12],
             ciphertext: [0; 48],
             api: options.api,
+            chain_id: options.chain_id,
         }
     }
     pub async fn select(&mut self) -> bool {
         let mut vec = vec!["Wallet", "Search", "Height", "API", "Exit"];
         if self.key.is_some() {
-            let mut v = vec!["Address", "Balance", "Send", "Stake", "Secret", "Hex"];
+            let mut v = vec![
+                "Address", "Balance", "History", "Send", "Batch", "Multisig", "HTLC", "Stake", "Delegate", "Secret", "Hex",
+            ];
             v.append(&mut vec);
             vec = v;
         };
@@ -65,14 +74,34 @@ impl Wallet {
                 self.balance().await;
                 true
             }
+            "History" => {
+                self.history().await;
+                true
+            }
             "Send" => {
                 self.transaction().await;
                 true
             }
+            "Batch" => {
+                self.batch().await;
+                true
+            }
+            "Multisig" => {
+                self.multisig().await;
+                true
+            }
+            "HTLC" => {
+                self.htlc().await;
+                true
+            }
             "Stake" => {
                 self.stake().await;
                 true
             }
+            "Delegate" => {
+                self.delegate().await;
+                true
+            }
             "Secret" => {
                 self.key();
                 true
@@ -116,6 +145,37 @@ impl Wallet {
             },
             Err(err) => println!("{}", err.to_string().red()),
         };
+        if let Ok(delegations) = get::delegations(&self.api, &address).await {
+            for x in delegations {
+                println!("Delegated: {} to {}", x.amount.yellow(), x.staker.green());
+            }
+        }
+        if let Ok(unbonding) = get::unbonding(&self.api, &address).await {
+            for x in unbonding {
+                println!("Unbonding: {}, released in {} blocks", x.amount.yellow(), x.blocks.to_string().yellow());
+            }
+        }
+    }
+    async fn history(&self) {
+        let address = address::encode(&self.key.as_ref().unwrap().address_bytes());
+        let limit = 10;
+        let mut offset = 0;
+        loop {
+            let history = match get::history(&self.api, &address, offset, limit).await {
+                Ok(x) => x,
+                Err(err) => {
+                    println!("{}", err.to_string().red());
+                    return;
+                }
+            };
+            for x in history.iter() {
+                println!("{} {:<5} {}", x.height.to_string().yellow(), x.direction, x.hash.green());
+            }
+            if history.len() < limit || !more() {
+                return;
+            }
+            offset += limit;
+        }
     }
     async fn height(&self) {
         match get::height(&self.api).await {
@@ -127,6 +187,7 @@ impl Wallet {
         let address = address();
         let amount = amount();
         let fee = fee();
+        let memo = memo();
         if !match Confirm::new("Send?").prompt() {
             Ok(b) => b,
             Err(err) => {
@@ -136,28 +197,326 @@ impl Wallet {
         } {
             return;
         }
-        let transaction_a = TransactionA::sign(address::decode(&address).unwrap(), amount, fee, util::timestamp(), self.key.as_ref().unwrap()).unwrap();
+        let chain_id = match self.chain_id().await {
+            Some(x) => x,
+            None => return,
+        };
+        let nonce = match self.nonce(&self.key.as_ref().unwrap().address_bytes()).await {
+            Some(x) => x,
+            None => return,
+        };
+        let transaction_a = TransactionA::sign_batch(
+            chain_id,
+            nonce,
+            vec![(address::decode(&address).unwrap(), amount)],
+            fee,
+            memo,
+            util::timestamp(),
+            self.key.as_ref().unwrap(),
+        )
+        .unwrap();
+        println!("Hash: {}", hex::encode(transaction_a.hash).cyan());
+        match post::transaction(&self.api, &transaction_a.b()).await {
+            Ok(res) => println!("{}", if res == "success" { res.green() } else { res.red() }),
+            Err(err) => println!("{}", err.to_string().red()),
+        };
+        self.transaction_status(&transaction_a.hash).await;
+    }
+    async fn transaction_status(&self, hash: &Hash) {
+        match get::transaction_status(&self.api, &hex::encode(hash)).await {
+            Ok(status) => {
+                let detail = match (status.block, status.height, status.replaced_by, status.reason) {
+                    (Some(block), Some(height), _, _) => format!(" in block {} at height {}", block, height),
+                    (_, _, Some(hash), _) => format!(" by {}", hash),
+                    (_, _, _, Some(reason)) => format!(": {}", reason),
+                    _ => "".to_string(),
+                };
+                println!("Status: {}{}", status.status.yellow(), detail);
+            }
+            Err(err) => println!("{}", err.to_string().red()),
+        };
+    }
+    async fn batch(&self) {
+        let outputs = match util::outputs(path()) {
+            Ok(x) => x,
+            Err(err) => {
+                println!("{}", err.to_string().red());
+                return;
+            }
+        };
+        let fee = fee();
+        let memo = memo();
+        println!(
+            "Outputs: {}, total: {}",
+            outputs.len().to_string().yellow(),
+            pea_int::to_string(outputs.iter().map(|(_, amount)| amount).sum()).yellow()
+        );
+        if !send() {
+            return;
+        }
+        let chain_id = match self.chain_id().await {
+            Some(x) => x,
+            None => return,
+        };
+        let nonce = match self.nonce(&self.key.as_ref().unwrap().address_bytes()).await {
+            Some(x) => x,
+            None => return,
+        };
+        let transaction_a = TransactionA::sign_batch(chain_id, nonce, outputs, fee, memo, util::timestamp(), self.key.as_ref().unwrap()).unwrap();
+        println!("Hash: {}", hex::encode(transaction_a.hash).cyan());
+        match post::transaction(&self.api, &transaction_a.b()).await {
+            Ok(res) => println!("{}", if res == "success" { res.green() } else { res.red() }),
+            Err(err) => println!("{}", err.to_string().red()),
+        };
+    }
+    async fn multisig(&self) {
+        match Select::new(">>", vec!["Address", "Create", "Sign", "Combine"]).prompt().unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        }) {
+            "Address" => self.multisig_address(),
+            "Create" => self.multisig_create().await,
+            "Sign" => self.multisig_sign(),
+            "Combine" => self.multisig_combine().await,
+            _ => {}
+        }
+    }
+    fn multisig_address(&self) {
+        let threshold = threshold();
+        let mut addresses = addresses();
+        addresses.sort();
+        addresses.dedup();
+        if threshold == 0 || threshold as usize > addresses.len() {
+            println!("{}", "Threshold must be between 1 and the number of addresses".red());
+            return;
+        }
+        println!("Address: {}", address::encode(&Key::multisig_address(threshold, &addresses)).green());
+        println!("Policy: {}", multisig::encode(threshold, &addresses).green());
+    }
+    async fn multisig_create(&self) {
+        let (threshold, addresses) = policy();
+        let address = address();
+        let amount = amount();
+        let fee = fee();
+        let memo = memo();
+        let input_address = Key::multisig_address(threshold, &addresses);
+        let chain_id = match self.chain_id().await {
+            Some(x) => x,
+            None => return,
+        };
+        let nonce = match self.nonce(&input_address).await {
+            Some(x) => x,
+            None => return,
+        };
+        let mut transaction_a = TransactionA {
+            chain_id,
+            nonce,
+            input_address,
+            outputs: vec![(address::decode(&address).unwrap(), amount)],
+            fee,
+            memo,
+            multisig: Some(Multisig {
+                threshold,
+                addresses,
+                signatures: vec![],
+            }),
+            timestamp: util::timestamp(),
+            ..TransactionA::default()
+        };
+        transaction_a.hash = transaction_a.hash();
+        if let Err(err) = transaction_a.multisig_sign(self.key.as_ref().unwrap()) {
+            println!("{}", err.to_string().red());
+        }
+        multisig_print(&transaction_a);
+    }
+    fn multisig_sign(&self) {
+        let mut transaction_a = util::multisig_decode(&multisig_transaction()).unwrap();
+        if transaction_a.chain_id != self.chain_id {
+            println!(
+                "{} {} {}",
+                "Chain id mismatch".red(),
+                self.chain_id.to_string().yellow(),
+                transaction_a.chain_id.to_string().red()
+            );
+            return;
+        }
+        match transaction_a.multisig_sign(self.key.as_ref().unwrap()) {
+            Ok(()) => multisig_print(&transaction_a),
+            Err(err) => println!("{}", err.to_string().red()),
+        }
+    }
+    async fn multisig_combine(&self) {
+        let mut transaction_a = util::multisig_decode(&multisig_transaction()).unwrap();
+        while another() {
+            if let Err(err) = transaction_a.multisig_combine(&util::multisig_decode(&multisig_transaction()).unwrap()) {
+                println!("{}", err.to_string().red());
+            }
+        }
+        multisig_print(&transaction_a);
+        if let Err(err) = transaction_a.multisig.as_ref().unwrap().verify(&transaction_a.hash) {
+            println!("{}", err.to_string().red());
+            return;
+        }
+        if !send() {
+            return;
+        }
+        match post::transaction(&self.api, &transaction_a.b()).await {
+            Ok(res) => println!("{}", if res == "success" { res.green() } else { res.red() }),
+            Err(err) => println!("{}", err.to_string().red()),
+        };
+    }
+    async fn htlc(&self) {
+        match Select::new(">>", vec!["Lock", "Claim", "Refund", "Status"]).prompt().unwrap_or_else(|err| {
+            println!("{}", err.to_string().red());
+            process::exit(0)
+        }) {
+            "Lock" => self.htlc_lock().await,
+            "Claim" => self.htlc_release(false).await,
+            "Refund" => self.htlc_release(true).await,
+            "Status" => self.htlc_status().await,
+            _ => {}
+        }
+    }
+    async fn htlc_lock(&self) {
+        let address = address();
+        let amount = amount();
+        let fee = fee();
+        let hash_lock = match hash_lock() {
+            Some(x) => x,
+            None => {
+                let preimage: [u8; 32] = rand::random();
+                println!("Secret: {}", hex::encode(preimage).red());
+                Htlc::hash_lock(&preimage)
+            }
+        };
+        let timeout = util::timestamp() + timeout() * 60 * 60;
+        if !send() {
+            return;
+        }
+        let chain_id = match self.chain_id().await {
+            Some(x) => x,
+            None => return,
+        };
+        let nonce = match self.nonce(&self.key.as_ref().unwrap().address_bytes()).await {
+            Some(x) => x,
+            None => return,
+        };
+        let transaction_a = TransactionA::sign_htlc(
+            chain_id,
+            nonce,
+            vec![(address::decode(&address).unwrap(), amount)],
+            fee,
+            Htlc::Lock { hash_lock, timeout },
+            util::timestamp(),
+            self.key.as_ref().unwrap(),
+        )
+        .unwrap();
+        println!("Hash: {}", hex::encode(transaction_a.hash).cyan());
+        println!("Hash lock: {}", hex::encode(hash_lock).cyan());
+        match post::transaction(&self.api, &transaction_a.b()).await {
+            Ok(res) => println!("{}", if res == "success" { res.green() } else { res.red() }),
+            Err(err) => println!("{}", err.to_string().red()),
+        };
+    }
+    async fn htlc_release(&self, refund: bool) {
+        let lock = lock();
+        let htlc = if refund {
+            Htlc::Refund { lock }
+        } else {
+            Htlc::Claim { lock, preimage: preimage() }
+        };
+        let fee = fee();
+        if !send() {
+            return;
+        }
+        let chain_id = match self.chain_id().await {
+            Some(x) => x,
+            None => return,
+        };
+        let nonce = match self.nonce(&self.key.as_ref().unwrap().address_bytes()).await {
+            Some(x) => x,
+            None => return,
+        };
+        let transaction_a = TransactionA::sign_htlc(chain_id, nonce, vec![], fee, htlc, util::timestamp(), self.key.as_ref().unwrap()).unwrap();
         println!("Hash: {}", hex::encode(transaction_a.hash).cyan());
         match post::transaction(&self.api, &transaction_a.b()).await {
             Ok(res) => println!("{}", if res == "success" { res.green() } else { res.red() }),
             Err(err) => println!("{}", err.to_string().red()),
         };
     }
+    async fn htlc_status(&self) {
+        match get::lock(&self.api, &hex::encode(lock())).await {
+            Ok(lock) => println!("{:?}", lock),
+            Err(err) => println!("{}", err.to_string().red()),
+        };
+    }
     async fn stake(&self) {
         let deposit = deposit();
         let amount = amount();
         let fee = fee();
+        let commission = commission();
+        let send = send();
+        if !send {
+            return;
+        }
+        let chain_id = match self.chain_id().await {
+            Some(x) => x,
+            None => return,
+        };
+        let stake_a = StakeA::sign_commission(chain_id, deposit, amount, fee, commission, util::timestamp(), self.key.as_ref().unwrap()).unwrap();
+        println!("Hash: {}", hex::encode(stake_a.hash).cyan());
+        match post::stake(&self.api, &stake_a.b()).await {
+            Ok(res) => println!("{}", if res == "success" { res.green() } else { res.red() }),
+            Err(err) => println!("{}", err.to_string().red()),
+        };
+    }
+    async fn delegate(&self) {
+        let deposit = deposit();
+        let staker = address::decode(&address()).unwrap();
+        let amount = amount();
+        let fee = fee();
         let send = send();
         if !send {
             return;
         }
-        let stake_a = StakeA::sign(deposit, amount, fee, util::timestamp(), self.key.as_ref().unwrap()).unwrap();
+        let chain_id = match self.chain_id().await {
+            Some(x) => x,
+            None => return,
+        };
+        let stake_a = StakeA::sign_delegate(chain_id, deposit, staker, amount, fee, util::timestamp(), self.key.as_ref().unwrap()).unwrap();
         println!("Hash: {}", hex::encode(stake_a.hash).cyan());
         match post::stake(&self.api, &stake_a.b()).await {
             Ok(res) => println!("{}", if res == "success" { res.green() } else { res.red() }),
             Err(err) => println!("{}", err.to_string().red()),
         };
     }
+    async fn chain_id(&self) -> Option<u32> {
+        match get::options(&self.api).await {
+            Ok(options) if options.chain_id == self.chain_id => Some(self.chain_id),
+            Ok(options) => {
+                println!(
+                    "{} {} {}",
+                    "Chain id mismatch".red(),
+                    self.chain_id.to_string().yellow(),
+                    options.chain_id.to_string().red()
+                );
+                None
+            }
+            Err(err) => {
+                println!("{}", err.to_string().red());
+                None
+            }
+        }
+    }
+    async fn nonce(&self, address: &AddressBytes) -> Option<u64> {
+        match get::nonce(&self.api, &address::encode(address)).await {
+            Ok(nonce) => Some(nonce),
+            Err(err) => {
+                println!("{}", err.to_string().red());
+                None
+            }
+        }
+    }
     async fn search(&self) {
         let search = search();
         if address::decode(&search).is_ok() {
@@ -214,9 +573,19 @@ impl Wallet {
     fn data(&self) {
         println!(
             "{}{}{}",
-            hex::encode(&self.salt).red(),
-            hex::encode(&self.nonce).red(),
-            hex::encode(&self.ciphertext).red()
+            hex::encode(self.salt).red(),
+            hex::encode(self.nonce).red(),
+            hex::encode(self.ciphertext).red()
         );
     }
 }
+fn multisig_print(transaction_a: &TransactionA) {
+    let multisig = transaction_a.multisig.as_ref().unwrap();
+    println!("Hash: {}", hex::encode(transaction_a.hash).cyan());
+    println!(
+        "Signatures: {}/{}",
+        multisig.signatures.len().to_string().yellow(),
+        multisig.threshold.to_string().yellow()
+    );
+    println!("{}", util::multisig_encode(transaction_a).unwrap().green());
+}